@@ -1,7 +1,15 @@
 use temporalio_macros::{activities, activity};
 use temporalio_sdk::activities::{ActivityContext, ActivityError};
+use tracing::{Span, info, info_span};
 
-use crate::{banking_client::BankingService, shared::PaymentDetails};
+use crate::{
+    approval::{self, ApprovalPolicy},
+    banking_client::{BankingError, BankingService},
+    fees::{FeeBreakdown, FeeEngine},
+    in_flight,
+    shared::PaymentDetails,
+    trace_context::Traced,
+};
 
 #[activity]
 #[derive(Default)]
@@ -9,63 +17,188 @@ pub struct Activities;
 
 #[activities]
 impl Activities {
+    /// Runs as a local activity ahead of any bank call; bad input fails without retrying.
     #[activity]
-    pub async fn withdraw(
+    pub async fn validate_payment(
         _ctx: ActivityContext,
         data: PaymentDetails,
+    ) -> Result<(), ActivityError> {
+        data.validate()
+            .map_err(|e| ActivityError::NonRetryable(e.into()))
+    }
+
+    /// Runs as a local activity so the worker's approval settings are recorded in the
+    /// workflow's history.
+    #[activity]
+    pub async fn approval_policy(
+        _ctx: ActivityContext,
+        _input: (),
+    ) -> Result<ApprovalPolicy, ActivityError> {
+        Ok(approval::policy())
+    }
+
+    #[activity]
+    pub async fn withdraw(
+        ctx: ActivityContext,
+        input: Traced<PaymentDetails>,
     ) -> Result<String, ActivityError> {
-        println!(
-            "Withdrawing {} from account {}.\n",
-            data.amount, data.source_account
-        );
+        let _in_flight = in_flight::track(&ctx);
+        let span = activity_span(&ctx, &input);
+        let data = input.value;
+        span.in_scope(|| {
+            info!(amount = %data.amount, account = %data.source_account, "withdrawing");
 
-        let reference_id = format!("{}-withdrawal", data.reference_id);
-        let bank = BankingService {
-            hostname: "bank-api.example.com".to_string(),
-        };
+            let reference_id = format!("{}-withdrawal", data.reference_id);
+            let bank = BankingService {
+                hostname: "bank-api.example.com".to_string(),
+            };
 
-        let confirmation = bank.withdraw(data.source_account, data.amount, reference_id)?;
+            let confirmation = bank
+                .withdraw(data.source_account, data.amount, reference_id)
+                .map_err(bank_failure)?;
 
-        Ok(confirmation)
+            Ok(confirmation)
+        })
     }
 
     #[activity]
     pub async fn deposit(
-        _ctx: ActivityContext,
-        data: PaymentDetails,
+        ctx: ActivityContext,
+        input: Traced<PaymentDetails>,
     ) -> Result<String, ActivityError> {
-        println!(
-            "Despositing {} into account {}.\n",
-            data.amount, data.target_account
-        );
+        let _in_flight = in_flight::track(&ctx);
+        let span = activity_span(&ctx, &input);
+        let data = input.value;
+        span.in_scope(|| {
+            info!(amount = %data.amount, account = %data.target_account, "depositing");
 
-        let reference_id = format!("{}-deposit", data.reference_id);
-        let bank = BankingService {
-            hostname: "bank-api.example.com".to_string(),
-        };
+            let reference_id = format!("{}-deposit", data.reference_id);
+            let bank = BankingService {
+                hostname: "bank-api.example.com".to_string(),
+            };
 
-        let confirmation = bank.deposit(data.source_account, data.amount, reference_id)?;
+            let confirmation = bank
+                .deposit(data.target_account, data.amount, reference_id)
+                .map_err(bank_failure)?;
 
-        Ok(confirmation)
+            Ok(confirmation)
+        })
     }
 
     #[activity]
     pub async fn refund(
-        _ctx: ActivityContext,
-        data: PaymentDetails,
+        ctx: ActivityContext,
+        input: Traced<PaymentDetails>,
     ) -> Result<String, ActivityError> {
-        println!(
-            "Refunding {} back into account {}.\n",
-            data.amount, data.source_account
-        );
+        let _in_flight = in_flight::track(&ctx);
+        let span = activity_span(&ctx, &input);
+        let data = input.value;
+        span.in_scope(|| {
+            info!(amount = %data.amount, account = %data.source_account, "refunding");
 
-        let reference_id = format!("{}-refund", data.reference_id);
-        let bank = BankingService {
-            hostname: "bank-api.example.com".to_string(),
-        };
+            let reference_id = format!("{}-refund", data.reference_id);
+            let bank = BankingService {
+                hostname: "bank-api.example.com".to_string(),
+            };
 
-        let confirmation = bank.deposit(data.target_account, data.amount, reference_id)?;
+            let confirmation = bank
+                .deposit(data.source_account, data.amount, reference_id)
+                .map_err(bank_failure)?;
 
-        Ok(confirmation)
+            Ok(confirmation)
+        })
     }
+
+    #[activity]
+    pub async fn calculate_fee(
+        ctx: ActivityContext,
+        input: Traced<PaymentDetails>,
+    ) -> Result<FeeBreakdown, ActivityError> {
+        let _in_flight = in_flight::track(&ctx);
+        let span = activity_span(&ctx, &input);
+        let data = input.value;
+        span.in_scope(|| {
+            let bank = BankingService {
+                hostname: "bank-api.example.com".to_string(),
+            };
+
+            let (account_type, currency) = bank
+                .account_profile(&data.source_account)
+                .map_err(bank_failure)?;
+            let breakdown = FeeEngine::standard()
+                .quote(account_type, &currency, data.amount)
+                .map_err(|e| ActivityError::NonRetryable(e.into()))?;
+
+            info!(
+                amount = %data.amount,
+                %currency,
+                account = %data.source_account,
+                fee = %breakdown.fee,
+                "fee calculated"
+            );
+
+            Ok(breakdown)
+        })
+    }
+
+    #[activity]
+    pub async fn post_fee(
+        ctx: ActivityContext,
+        input: Traced<PaymentDetails>,
+    ) -> Result<String, ActivityError> {
+        let _in_flight = in_flight::track(&ctx);
+        let span = activity_span(&ctx, &input);
+        let data = input.value;
+        span.in_scope(|| {
+            info!(amount = %data.amount, account = %data.target_account, "posting fee");
+
+            let reference_id = format!("{}-fee", data.reference_id);
+            let bank = BankingService {
+                hostname: "bank-api.example.com".to_string(),
+            };
+
+            let confirmation = bank
+                .deposit(data.target_account, data.amount, reference_id)
+                .map_err(bank_failure)?;
+
+            Ok(confirmation)
+        })
+    }
+}
+
+/// The activity failure for an error the bank answered with.
+///
+/// The SDK doesn't give activity failures an error type, so a retry policy's
+/// `non_retryable_error_types` can never match them: errors that retrying can't fix have
+/// to be marked non-retryable here.
+fn bank_failure(error: BankingError) -> ActivityError {
+    match error {
+        BankingError::InsufficientFunds(_) | BankingError::InvalidAccount(_) => {
+            ActivityError::NonRetryable(error.into())
+        }
+    }
+}
+
+/// A span tying an activity's events, and the bank's, to its workflow and attempt, so one
+/// transfer's logs can be followed across retries. When tracing, it continues the trace
+/// the workflow was started in.
+fn activity_span(ctx: &ActivityContext, input: &Traced<PaymentDetails>) -> Span {
+    let info = ctx.info();
+    let (workflow_id, run_id) = info
+        .workflow_execution
+        .as_ref()
+        .map_or(("", ""), |execution| {
+            (execution.workflow_id.as_str(), execution.run_id.as_str())
+        });
+    let span = info_span!(
+        "activity",
+        otel.name = %format!("RunActivity:{}", info.activity_type),
+        workflow_id,
+        run_id,
+        activity_type = %info.activity_type,
+        attempt = info.attempt,
+        reference_id = %input.value.reference_id,
+    );
+    input.trace_context.set_as_parent_of(&span);
+    span
 }
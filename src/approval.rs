@@ -0,0 +1,51 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_APPROVAL_THRESHOLD: Decimal = Decimal::ONE_THOUSAND;
+pub const DEFAULT_APPROVAL_TIMEOUT: Duration = Duration::from_secs(72 * 60 * 60);
+
+/// Which transfers wait for an approver, and for how long.
+///
+/// The worker installs its policy at startup. The workflow reads it through the
+/// `approval_policy` local activity, so the answer is recorded in history and a replay
+/// on a worker with different settings still makes the same decision.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct ApprovalPolicy {
+    /// Transfers of more than this amount wait for an `approve` or `reject` signal.
+    pub threshold: Decimal,
+    /// How long a transfer waits for a decision before it is rejected.
+    pub timeout: Duration,
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_APPROVAL_THRESHOLD,
+            timeout: DEFAULT_APPROVAL_TIMEOUT,
+        }
+    }
+}
+
+impl ApprovalPolicy {
+    pub fn requires_approval(&self, amount: Decimal) -> bool {
+        amount > self.threshold
+    }
+}
+
+static POLICY: OnceLock<ApprovalPolicy> = OnceLock::new();
+
+/// Use `policy` for every transfer this process starts from now on.
+///
+/// Until this is called, and in any process that never calls it (tests), the default
+/// policy applies. Only the first call has any effect.
+pub fn install(policy: ApprovalPolicy) {
+    POLICY.get_or_init(|| policy);
+}
+
+/// The policy installed by [`install`], or the default one.
+pub fn policy() -> ApprovalPolicy {
+    POLICY.get().copied().unwrap_or_default()
+}
@@ -1,6 +1,11 @@
 use rand::Rng;
 use rust_decimal::Decimal;
 use std::sync::LazyLock;
+use tracing::{debug, info, info_span};
+
+use crate::fees::{AccountType, EUR_FEE_ACCOUNT, FEE_ACCOUNT};
+use crate::metrics::time_bank_call;
+use crate::trace_context::TraceContext;
 
 // ---------------------------------------------------------------------------
 // Error types
@@ -36,6 +41,8 @@ pub enum BankingError {
 pub struct Account {
     pub account_number: String,
     pub balance: Decimal,
+    pub account_type: AccountType,
+    pub currency: String,
 }
 
 pub struct Bank {
@@ -58,10 +65,39 @@ static MOCK_BANK: LazyLock<Bank> = LazyLock::new(|| Bank {
         Account {
             account_number: "85-150".to_string(),
             balance: Decimal::from(2000),
+            account_type: AccountType::Personal,
+            currency: "USD".to_string(),
         },
         Account {
             account_number: "43-812".to_string(),
             balance: Decimal::from(0),
+            account_type: AccountType::Personal,
+            currency: "USD".to_string(),
+        },
+        // Intermediaries for routed transfers: a clearing account and a correspondent bank.
+        Account {
+            account_number: "10-001".to_string(),
+            balance: Decimal::from(0),
+            account_type: AccountType::Business,
+            currency: "USD".to_string(),
+        },
+        Account {
+            account_number: "20-002".to_string(),
+            balance: Decimal::from(0),
+            account_type: AccountType::Business,
+            currency: "USD".to_string(),
+        },
+        Account {
+            account_number: FEE_ACCOUNT.to_string(),
+            balance: Decimal::from(0),
+            account_type: AccountType::Business,
+            currency: "USD".to_string(),
+        },
+        Account {
+            account_number: EUR_FEE_ACCOUNT.to_string(),
+            balance: Decimal::from(0),
+            account_type: AccountType::Business,
+            currency: "EUR".to_string(),
         },
     ],
 });
@@ -81,20 +117,36 @@ impl BankingService {
         amount: Decimal,
         reference_id: String,
     ) -> Result<String, BankingError> {
-        let account = MOCK_BANK.find_account(&account_number)?;
-        if account.balance < amount {
-            return Err(InsufficientFundsError {
-                current_balance: account.balance,
-                attempted_withdrawal: amount,
+        self.request("withdraw", || {
+            let account = MOCK_BANK.find_account(&account_number)?;
+            if account.balance < amount {
+                return Err(InsufficientFundsError {
+                    current_balance: account.balance,
+                    attempted_withdrawal: amount,
+                }
+                .into());
             }
-            .into());
-        }
-        let confirmation = generate_transaction_id("W", 10);
-        println!(
-            "Withdrawal of {} from account {} accepted. Confirmation: {}. ReferenceId: {}",
-            amount, account_number, confirmation, reference_id
-        );
-        Ok(confirmation)
+            let confirmation = generate_transaction_id("W", 10);
+            info!(
+                %amount,
+                account = %account_number,
+                %confirmation,
+                %reference_id,
+                "withdrawal accepted"
+            );
+            Ok(confirmation)
+        })
+    }
+
+    /// Look up the type and currency of an account.
+    pub fn account_profile(
+        &self,
+        account_number: &str,
+    ) -> Result<(AccountType, String), BankingError> {
+        self.request("account_profile", || {
+            let account = MOCK_BANK.find_account(account_number)?;
+            Ok((account.account_type, account.currency.clone()))
+        })
     }
 
     pub fn deposit(
@@ -103,13 +155,43 @@ impl BankingService {
         amount: Decimal,
         reference_id: String,
     ) -> Result<String, BankingError> {
-        let _ = MOCK_BANK.find_account(&account_number)?;
-        let confirmation = generate_transaction_id("D", 10);
-        println!(
-            "Deposit of {} to account {} accepted. Confirmation: {}. ReferenceId: {}",
-            amount, account_number, confirmation, reference_id
+        self.request("deposit", || {
+            let _ = MOCK_BANK.find_account(&account_number)?;
+            let confirmation = generate_transaction_id("D", 10);
+            info!(
+                %amount,
+                account = %account_number,
+                %confirmation,
+                %reference_id,
+                "deposit accepted"
+            );
+            Ok(confirmation)
+        })
+    }
+
+    /// Make one request to the bank in its own client span, a child of the activity's.
+    ///
+    /// The mock bank is called in-process, so no trace context leaves the worker. A real
+    /// client would put [`TraceContext::current`] in the request's `traceparent` and
+    /// `tracestate` headers here for the bank's own spans to join the transfer's trace;
+    /// it is only logged at debug level.
+    fn request<T>(
+        &self,
+        operation: &'static str,
+        call: impl FnOnce() -> Result<T, BankingError>,
+    ) -> Result<T, BankingError> {
+        let span = info_span!(
+            "bank request",
+            otel.name = %format!("Bank:{operation}"),
+            otel.kind = "client",
+            bank = %self.hostname,
+            operation,
         );
-        Ok(confirmation)
+        span.in_scope(|| {
+            let headers = TraceContext::current();
+            debug!(traceparent = headers.traceparent(), "calling the bank");
+            time_bank_call(operation, call)
+        })
     }
 
     pub fn deposit_that_fails(
@@ -0,0 +1,271 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use crate::shared::PaymentDetails;
+
+// ---------------------------------------------------------------------------
+// Error types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown import format {0:?}, expected `csv` or `jsonl`")]
+pub struct UnknownFormatError(pub String);
+
+/// A row that can't be imported, with the 1-based line it came from.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}: {message}")]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A header row naming `amount`, `source_account`, `target_account` and
+    /// `reference_id`, then one transfer per row.
+    Csv,
+    /// One `PaymentDetails` JSON object per line; blank lines are skipped.
+    JsonLines,
+}
+
+impl ImportFormat {
+    /// Guess the format from a file extension.
+    ///
+    /// A `.json` file is rejected rather than read as JSON-lines: it usually holds a
+    /// single array, which would fail on every line.
+    pub fn from_path(path: &Path) -> Result<Self, UnknownFormatError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        match extension {
+            "ndjson" => Ok(ImportFormat::JsonLines),
+            other => other.parse(),
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = UnknownFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ImportFormat::Csv),
+            "jsonl" => Ok(ImportFormat::JsonLines),
+            other => Err(UnknownFormatError(other.to_string())),
+        }
+    }
+}
+
+/// A valid transfer read from an import file.
+#[derive(Clone)]
+pub struct ImportRow {
+    pub line: usize,
+    pub details: PaymentDetails,
+}
+
+/// Parse and validate every row of an import file.
+///
+/// Rows are rejected if they don't parse, fail [`PaymentDetails::validate`], or reuse a
+/// reference id from an earlier row (they would map to the same workflow id).
+pub fn parse_transfers(contents: &str, format: ImportFormat) -> (Vec<ImportRow>, Vec<RowError>) {
+    let parsed = match format {
+        ImportFormat::Csv => parse_csv(contents),
+        ImportFormat::JsonLines => parse_json_lines(contents),
+    };
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    for (line, details) in parsed {
+        let result = details.and_then(|details| {
+            details.validate().map_err(|e| e.to_string())?;
+            if let Some(first) = first_seen.get(&details.reference_id) {
+                return Err(format!(
+                    "duplicate reference id {:?}, first used on line {first}",
+                    details.reference_id
+                ));
+            }
+            first_seen.insert(details.reference_id.clone(), line);
+            Ok(details)
+        });
+        match result {
+            Ok(details) => rows.push(ImportRow { line, details }),
+            Err(message) => errors.push(RowError { line, message }),
+        }
+    }
+    (rows, errors)
+}
+
+fn parse_csv(contents: &str) -> Vec<(usize, Result<PaymentDetails, String>)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes());
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => return vec![(1, Err(e.to_string()))],
+    };
+
+    reader
+        .records()
+        .map(|record| match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line() as usize);
+                let details = record
+                    .deserialize::<PaymentDetails>(Some(&headers))
+                    .map_err(|e| e.to_string());
+                (line, details)
+            }
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line() as usize);
+                (line, Err(e.to_string()))
+            }
+        })
+        .collect()
+}
+
+fn parse_json_lines(contents: &str) -> Vec<(usize, Result<PaymentDetails, String>)> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let details = serde_json::from_str::<PaymentDetails>(line).map_err(|e| e.to_string());
+            (i + 1, details)
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Results
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RowStatus {
+    Started,
+    /// A previous import started this transfer, and it is still running.
+    AlreadyStarted,
+    Invalid,
+    Failed,
+}
+
+/// One line of the results file.
+#[derive(Debug, Serialize)]
+pub struct RowResult {
+    pub line: usize,
+    pub status: RowStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RowResult {
+    /// The result for a row that was never started because it didn't validate.
+    pub fn invalid(error: RowError) -> Self {
+        RowResult {
+            line: error.line,
+            status: RowStatus::Invalid,
+            reference_id: None,
+            workflow_id: None,
+            run_id: None,
+            error: Some(error.message),
+        }
+    }
+
+    /// The result for a valid row, as if starting it succeeds.
+    pub fn started(row: &ImportRow) -> Self {
+        RowResult {
+            line: row.line,
+            status: RowStatus::Started,
+            reference_id: Some(row.details.reference_id.clone()),
+            workflow_id: Some(row.details.workflow_id()),
+            run_id: None,
+            error: None,
+        }
+    }
+}
+
+/// How many rows ended up in each status, and where their results were written.
+#[derive(Debug, Serialize, Default)]
+pub struct Summary {
+    pub started: usize,
+    pub already_started: usize,
+    pub invalid: usize,
+    pub failed: usize,
+    pub results: PathBuf,
+}
+
+impl Summary {
+    pub fn tally(results: &[RowResult], path: PathBuf) -> Self {
+        let mut summary = Summary {
+            results: path,
+            ..Default::default()
+        };
+        for result in results {
+            match result.status {
+                RowStatus::Started => summary.started += 1,
+                RowStatus::AlreadyStarted => summary.already_started += 1,
+                RowStatus::Invalid => summary.invalid += 1,
+                RowStatus::Failed => summary.failed += 1,
+            }
+        }
+        summary
+    }
+
+    /// 0 when every row was started (now, or by an earlier import and still running), 2
+    /// when some rows were invalid, and 1 when any valid row failed to start.
+    pub fn exit_code(&self) -> ExitCode {
+        if self.failed > 0 {
+            ExitCode::FAILURE
+        } else if self.invalid > 0 {
+            ExitCode::from(2)
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} started, {} already started, {} invalid, {} failed; results written to {}",
+            self.started,
+            self.already_started,
+            self.invalid,
+            self.failed,
+            self.results.display()
+        )
+    }
+}
+
+/// `<file>.results.jsonl`, next to the import file.
+pub fn default_results_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".results.jsonl");
+    PathBuf::from(name)
+}
+
+/// Write one JSON line per result.
+pub fn write_results(results: &[RowResult], mut out: impl Write) -> std::io::Result<()> {
+    for result in results {
+        serde_json::to_writer(&mut out, result)?;
+        writeln!(out)?;
+    }
+    out.flush()
+}
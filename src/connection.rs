@@ -0,0 +1,294 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use temporalio_client::{
+    Client, ClientOptions, ClientTlsOptions, Connection, ConnectionOptions, TlsOptions,
+};
+use temporalio_common::telemetry::metrics::TemporalMeter;
+use temporalio_sdk_core::Url;
+
+use crate::overrides::set;
+
+pub const DEFAULT_ADDRESS: &str = "http://localhost:7233";
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+// ---------------------------------------------------------------------------
+// Error types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectionConfigError {
+    #[error("can't read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("can't parse connection config {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    #[error("invalid server address {address:?}: {source}")]
+    InvalidAddress {
+        address: String,
+        source: url::ParseError,
+    },
+    #[error("mTLS needs both a client certificate and its private key")]
+    IncompleteClientIdentity,
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// How to reach the Temporal server, shared by the worker and the starter.
+///
+/// Loaded from a TOML file, then overridden field by field by flags and environment
+/// variables (see [`ConnectionArgs`]):
+///
+/// ```toml
+/// address = "https://payments.tmprl.example.com:7233"
+/// namespace = "payments"
+/// api_key = "..."
+///
+/// [tls]
+/// ca_cert = "/etc/temporal/ca.pem"
+/// client_cert = "/etc/temporal/client.pem"
+/// client_key = "/etc/temporal/client.key"
+/// server_name = "payments.internal"
+/// ```
+#[derive(Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionConfig {
+    /// Defaults to [`DEFAULT_ADDRESS`].
+    pub address: Option<String>,
+    /// Defaults to [`DEFAULT_NAMESPACE`].
+    pub namespace: Option<String>,
+    /// Sent as a bearer token on every call. Turns TLS on unless it is explicitly disabled.
+    pub api_key: Option<String>,
+    pub tls: TlsConfig,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// Force TLS on or off. When unset, TLS is used if an API key or any other TLS
+    /// setting is present.
+    pub enabled: Option<bool>,
+    /// PEM CA certificate to verify the server with, instead of the system roots.
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate for mTLS; needs `client_key` too.
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key for `client_cert`.
+    pub client_key: Option<PathBuf>,
+    /// Name to verify the server certificate against, when it differs from the address.
+    pub server_name: Option<String>,
+}
+
+/// Shows whether an API key is set, never the key itself.
+impl std::fmt::Debug for ConnectionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionConfig")
+            .field("address", &self.address)
+            .field("namespace", &self.namespace)
+            .field("api_key", &redacted(&self.api_key))
+            .field("tls", &self.tls)
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    fn has_settings(&self) -> bool {
+        self.ca_cert.is_some()
+            || self.client_cert.is_some()
+            || self.client_key.is_some()
+            || self.server_name.is_some()
+    }
+}
+
+impl ConnectionConfig {
+    /// Read a TOML connection config file.
+    pub fn from_file(path: &Path) -> Result<Self, ConnectionConfigError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|source| ConnectionConfigError::Read {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        toml::from_str(&contents).map_err(|source| ConnectionConfigError::Parse {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })
+    }
+
+    pub fn namespace(&self) -> &str {
+        self.namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE)
+    }
+
+    pub fn tls_enabled(&self) -> bool {
+        self.tls
+            .enabled
+            .unwrap_or(self.api_key.is_some() || self.tls.has_settings())
+    }
+
+    /// Build the connection options, reading any certificate files.
+    ///
+    /// With TLS on, an `http://` address is switched to `https://`, since the gRPC
+    /// transport only negotiates TLS for https URLs.
+    pub fn connection_options(
+        &self,
+        identity: &str,
+    ) -> Result<ConnectionOptions, ConnectionConfigError> {
+        let address = self.address.as_deref().unwrap_or(DEFAULT_ADDRESS);
+        let mut target =
+            Url::parse(address).map_err(|source| ConnectionConfigError::InvalidAddress {
+                address: address.to_string(),
+                source,
+            })?;
+
+        let tls_options = if self.tls_enabled() {
+            if target.scheme() == "http" {
+                target
+                    .set_scheme("https")
+                    .expect("http and https are interchangeable schemes");
+            }
+            Some(self.tls_options()?)
+        } else {
+            None
+        };
+
+        Ok(ConnectionOptions::new(target)
+            .identity(identity.to_string())
+            .maybe_tls_options(tls_options)
+            .maybe_api_key(self.api_key.clone())
+            .build())
+    }
+
+    fn tls_options(&self) -> Result<TlsOptions, ConnectionConfigError> {
+        let client_tls_options = match (&self.tls.client_cert, &self.tls.client_key) {
+            (Some(cert), Some(key)) => Some(ClientTlsOptions {
+                client_cert: read(cert)?,
+                client_private_key: read(key)?,
+            }),
+            (None, None) => None,
+            _ => return Err(ConnectionConfigError::IncompleteClientIdentity),
+        };
+        Ok(TlsOptions {
+            server_root_ca_cert: self.tls.ca_cert.as_deref().map(read).transpose()?,
+            domain: self.tls.server_name.clone(),
+            client_tls_options,
+        })
+    }
+
+    /// Connect and return a client for the configured namespace.
+    pub async fn connect(&self, identity: &str) -> Result<Client, Box<dyn std::error::Error>> {
+        self.connect_with_metrics(identity, None).await
+    }
+
+    /// Like [`Self::connect`], recording the client's request metrics through `meter`.
+    pub async fn connect_with_metrics(
+        &self,
+        identity: &str,
+        meter: Option<TemporalMeter>,
+    ) -> Result<Client, Box<dyn std::error::Error>> {
+        let mut options = self.connection_options(identity)?;
+        options.metrics_meter = meter;
+        let connection = Connection::connect(options).await?;
+        Ok(Client::new(
+            connection,
+            ClientOptions::new(self.namespace().to_string()).build(),
+        )?)
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, ConnectionConfigError> {
+    std::fs::read(path).map_err(|source| ConnectionConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn redacted(secret: &Option<String>) -> Option<&'static str> {
+    secret.as_ref().map(|_| "<redacted>")
+}
+
+// ---------------------------------------------------------------------------
+// Command-line flags
+// ---------------------------------------------------------------------------
+
+/// Connection flags shared by the worker and the starter. Each one overrides the matching
+/// field of the `--connection-config` file.
+#[derive(clap::Args, Clone, Default)]
+pub struct ConnectionArgs {
+    /// TOML file with connection settings.
+    #[arg(long, env = "TEMPORAL_CONNECTION_CONFIG", global = true)]
+    connection_config: Option<PathBuf>,
+
+    /// Temporal frontend address [default: http://localhost:7233].
+    #[arg(long, env = "TEMPORAL_ADDRESS", global = true)]
+    address: Option<String>,
+
+    /// Temporal namespace [default: default].
+    #[arg(long, env = "TEMPORAL_NAMESPACE", global = true)]
+    namespace: Option<String>,
+
+    /// API key sent as a bearer token; turns TLS on.
+    #[arg(long, env = "TEMPORAL_API_KEY", hide_env_values = true, global = true)]
+    api_key: Option<String>,
+
+    /// Force TLS on or off.
+    #[arg(long, env = "TEMPORAL_TLS", value_name = "true|false", global = true)]
+    tls: Option<bool>,
+
+    /// PEM CA certificate to verify the server with.
+    #[arg(long, env = "TEMPORAL_TLS_CA_CERT", global = true)]
+    tls_ca_cert: Option<PathBuf>,
+
+    /// PEM client certificate for mTLS.
+    #[arg(long, env = "TEMPORAL_TLS_CLIENT_CERT", global = true)]
+    tls_client_cert: Option<PathBuf>,
+
+    /// PEM private key for the client certificate.
+    #[arg(long, env = "TEMPORAL_TLS_CLIENT_KEY", global = true)]
+    tls_client_key: Option<PathBuf>,
+
+    /// Name to verify the server certificate against.
+    #[arg(long, env = "TEMPORAL_TLS_SERVER_NAME", global = true)]
+    tls_server_name: Option<String>,
+}
+
+/// Shows whether an API key was given, never the key itself.
+impl std::fmt::Debug for ConnectionArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionArgs")
+            .field("connection_config", &self.connection_config)
+            .field("address", &self.address)
+            .field("namespace", &self.namespace)
+            .field("api_key", &redacted(&self.api_key))
+            .field("tls", &self.tls)
+            .field("tls_ca_cert", &self.tls_ca_cert)
+            .field("tls_client_cert", &self.tls_client_cert)
+            .field("tls_client_key", &self.tls_client_key)
+            .field("tls_server_name", &self.tls_server_name)
+            .finish()
+    }
+}
+
+impl ConnectionArgs {
+    /// The config file, if any, with every flag or environment variable that was set
+    /// applied on top.
+    pub fn config(&self) -> Result<ConnectionConfig, ConnectionConfigError> {
+        let mut config = match &self.connection_config {
+            Some(path) => ConnectionConfig::from_file(path)?,
+            None => ConnectionConfig::default(),
+        };
+        set(&mut config.address, &self.address);
+        set(&mut config.namespace, &self.namespace);
+        set(&mut config.api_key, &self.api_key);
+        set(&mut config.tls.enabled, &self.tls);
+        set(&mut config.tls.ca_cert, &self.tls_ca_cert);
+        set(&mut config.tls.client_cert, &self.tls_client_cert);
+        set(&mut config.tls.client_key, &self.tls_client_key);
+        set(&mut config.tls.server_name, &self.tls_server_name);
+        Ok(config)
+    }
+}
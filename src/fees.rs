@@ -0,0 +1,229 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+
+/// Account that collects transfer fees charged in USD.
+pub const FEE_ACCOUNT: &str = "99-000";
+
+/// Account that collects transfer fees charged in EUR.
+pub const EUR_FEE_ACCOUNT: &str = "99-001";
+
+// ---------------------------------------------------------------------------
+// Error types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+#[error("no fee schedule configured for {account_type:?} accounts in {currency}")]
+pub struct NoFeeScheduleError {
+    pub account_type: AccountType,
+    pub currency: String,
+}
+
+// ---------------------------------------------------------------------------
+// Schedules
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountType {
+    Personal,
+    Business,
+}
+
+/// How a computed fee is rounded to the currency's minor unit.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoundingRule {
+    /// Round half away from zero (1.005 → 1.01).
+    HalfUp,
+    /// Banker's rounding (1.005 → 1.00, 1.015 → 1.02).
+    HalfEven,
+    /// Always round away from zero (1.001 → 1.01).
+    Up,
+    /// Always truncate (1.009 → 1.00).
+    Down,
+}
+
+impl RoundingRule {
+    pub fn apply(self, value: Decimal, scale: u32) -> Decimal {
+        let strategy = match self {
+            RoundingRule::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingRule::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingRule::Up => RoundingStrategy::AwayFromZero,
+            RoundingRule::Down => RoundingStrategy::ToZero,
+        };
+        value.round_dp_with_strategy(scale, strategy)
+    }
+}
+
+/// One band of a tiered schedule: the part of the amount above the previous band's
+/// `up_to`, and up to and including this one's, pays `rate`. The last tier should leave
+/// `up_to` unset.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct FeeTier {
+    pub up_to: Option<Decimal>,
+    pub rate: Decimal,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum FeeSchedule {
+    /// The same fee regardless of amount.
+    Flat { amount: Decimal },
+    /// A fraction of the amount (`0.01` is 1%), optionally clamped.
+    Percentage {
+        rate: Decimal,
+        minimum: Option<Decimal>,
+        maximum: Option<Decimal>,
+    },
+    /// `base` plus each tier's rate on the part of the amount within it, so the fee
+    /// never jumps at a tier boundary.
+    Tiered { base: Decimal, tiers: Vec<FeeTier> },
+}
+
+impl FeeSchedule {
+    /// The fee before rounding.
+    pub fn unrounded_fee(&self, amount: Decimal) -> Decimal {
+        match self {
+            FeeSchedule::Flat { amount: fee } => *fee,
+            FeeSchedule::Percentage {
+                rate,
+                minimum,
+                maximum,
+            } => {
+                let mut fee = amount * rate;
+                if let Some(minimum) = minimum {
+                    fee = fee.max(*minimum);
+                }
+                if let Some(maximum) = maximum {
+                    fee = fee.min(*maximum);
+                }
+                fee
+            }
+            FeeSchedule::Tiered { base, tiers } => {
+                let mut fee = *base;
+                let mut lower = Decimal::ZERO;
+                for tier in tiers {
+                    let upper = tier.up_to.map_or(amount, |up_to| up_to.min(amount));
+                    if upper > lower {
+                        fee += (upper - lower) * tier.rate;
+                    }
+                    match tier.up_to {
+                        Some(up_to) if up_to < amount => lower = up_to,
+                        _ => break,
+                    }
+                }
+                fee
+            }
+        }
+    }
+}
+
+/// A schedule applied to every transfer out of a given account type and currency.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct FeeRule {
+    pub account_type: AccountType,
+    pub currency: String,
+    pub schedule: FeeSchedule,
+    pub rounding: RoundingRule,
+    /// Account the fee is credited to, held in the rule's currency.
+    pub fee_account: String,
+}
+
+/// The fee charged on a transfer and how it was arrived at.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct FeeBreakdown {
+    pub account_type: AccountType,
+    pub currency: String,
+    pub schedule: FeeSchedule,
+    pub rounding: RoundingRule,
+    pub unrounded_fee: Decimal,
+    pub fee: Decimal,
+    pub fee_account: String,
+}
+
+// ---------------------------------------------------------------------------
+// FeeEngine
+// ---------------------------------------------------------------------------
+
+pub struct FeeEngine {
+    /// Number of decimal places fees are rounded to.
+    pub scale: u32,
+    pub rules: Vec<FeeRule>,
+}
+
+impl FeeEngine {
+    /// The schedules charged by this bank.
+    pub fn standard() -> Self {
+        Self {
+            scale: 2,
+            rules: vec![
+                FeeRule {
+                    account_type: AccountType::Personal,
+                    currency: "USD".to_string(),
+                    schedule: FeeSchedule::Tiered {
+                        base: Decimal::new(50, 2), // 0.50
+                        tiers: vec![
+                            FeeTier {
+                                up_to: Some(Decimal::from(100)),
+                                rate: Decimal::ZERO,
+                            },
+                            FeeTier {
+                                up_to: Some(Decimal::from(10_000)),
+                                rate: Decimal::new(1, 2), // 1%
+                            },
+                            FeeTier {
+                                up_to: None,
+                                rate: Decimal::new(5, 3), // 0.5%
+                            },
+                        ],
+                    },
+                    rounding: RoundingRule::HalfUp,
+                    fee_account: FEE_ACCOUNT.to_string(),
+                },
+                FeeRule {
+                    account_type: AccountType::Business,
+                    currency: "USD".to_string(),
+                    schedule: FeeSchedule::Percentage {
+                        rate: Decimal::new(25, 4), // 0.25%
+                        minimum: Some(Decimal::from(1)),
+                        maximum: Some(Decimal::from(25)),
+                    },
+                    rounding: RoundingRule::HalfEven,
+                    fee_account: FEE_ACCOUNT.to_string(),
+                },
+                FeeRule {
+                    account_type: AccountType::Business,
+                    currency: "EUR".to_string(),
+                    schedule: FeeSchedule::Flat {
+                        amount: Decimal::from(2),
+                    },
+                    rounding: RoundingRule::HalfEven,
+                    fee_account: EUR_FEE_ACCOUNT.to_string(),
+                },
+            ],
+        }
+    }
+
+    pub fn quote(
+        &self,
+        account_type: AccountType,
+        currency: &str,
+        amount: Decimal,
+    ) -> Result<FeeBreakdown, NoFeeScheduleError> {
+        let rule = self
+            .rules
+            .iter()
+            .find(|r| r.account_type == account_type && r.currency == currency)
+            .ok_or_else(|| NoFeeScheduleError {
+                account_type,
+                currency: currency.to_string(),
+            })?;
+        let unrounded_fee = rule.schedule.unrounded_fee(amount);
+        Ok(FeeBreakdown {
+            account_type,
+            currency: currency.to_string(),
+            schedule: rule.schedule.clone(),
+            rounding: rule.rounding,
+            unrounded_fee,
+            fee: rule.rounding.apply(unrounded_fee, self.scale),
+            fee_account: rule.fee_account.clone(),
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use temporalio_sdk::activities::ActivityContext;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static RUNNING: Mutex<BTreeMap<u64, InFlightActivity>> = Mutex::new(BTreeMap::new());
+
+/// An activity attempt this process is currently running.
+#[derive(Clone, Debug)]
+pub struct InFlightActivity {
+    pub activity_type: String,
+    pub workflow_id: String,
+    pub attempt: u32,
+    started: Instant,
+}
+
+impl InFlightActivity {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl std::fmt::Display for InFlightActivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} for {} (attempt {}, running {:.1}s)",
+            self.activity_type,
+            self.workflow_id,
+            self.attempt,
+            self.elapsed().as_secs_f64()
+        )
+    }
+}
+
+/// Keeps an activity in [`running`] until dropped.
+#[must_use]
+pub struct InFlightGuard(u64);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.0);
+    }
+}
+
+/// Record the activity behind `ctx` as running until the returned guard is dropped, so a
+/// worker shutting down can say what it left unfinished.
+pub fn track(ctx: &ActivityContext) -> InFlightGuard {
+    let info = ctx.info();
+    let activity = InFlightActivity {
+        activity_type: info.activity_type.clone(),
+        workflow_id: info
+            .workflow_execution
+            .as_ref()
+            .map(|execution| execution.workflow_id.clone())
+            .unwrap_or_default(),
+        attempt: info.attempt,
+        started: Instant::now(),
+    };
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(id, activity);
+    InFlightGuard(id)
+}
+
+/// Activities still running in this process, oldest first.
+pub fn running() -> Vec<InFlightActivity> {
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .cloned()
+        .collect()
+}
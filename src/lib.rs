@@ -1,4 +1,17 @@
 pub mod activity;
+pub mod approval;
 pub mod banking_client;
+pub mod bulk;
+pub mod connection;
+pub mod fees;
+pub mod in_flight;
+pub mod logging;
+pub mod metrics;
+mod overrides;
+pub mod search_attributes;
 pub mod shared;
+pub mod starter;
+pub mod timeline;
+pub mod trace_context;
+pub mod worker_config;
 pub mod workflow;
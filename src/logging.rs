@@ -0,0 +1,60 @@
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use serde::Deserialize;
+use tracing_subscriber::{EnvFilter, Layer, filter::ParseError, prelude::*};
+
+use crate::trace_context::otel_layer;
+
+/// Filter used when neither `RUST_LOG` nor a configured filter is set.
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+// ---------------------------------------------------------------------------
+// Error types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoggingError {
+    #[error("invalid log filter: {0}")]
+    Filter(#[from] ParseError),
+    #[error("can't install the logger: {0}")]
+    Install(String),
+}
+
+// ---------------------------------------------------------------------------
+// Output
+// ---------------------------------------------------------------------------
+
+/// How the worker writes its logs.
+#[derive(clap::ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// One line per event, with the fields of its spans inline.
+    #[default]
+    Text,
+    /// Several indented lines per event, easier to read by eye.
+    Pretty,
+    /// One JSON object per event, with its spans' fields, for log collectors.
+    Json,
+}
+
+/// Install the global `tracing` subscriber, which also receives the SDK's own events.
+///
+/// `filter` uses the `RUST_LOG` syntax, for example `info,temporalio_sdk_core=warn`. With
+/// a `tracer`, spans are exported through it as well.
+pub fn init(
+    format: LogFormat,
+    filter: &str,
+    tracer: Option<&SdkTracerProvider>,
+) -> Result<(), LoggingError> {
+    let output = tracing_subscriber::fmt::layer();
+    let output = match format {
+        LogFormat::Text => output.boxed(),
+        LogFormat::Pretty => output.pretty().boxed(),
+        LogFormat::Json => output.json().flatten_event(true).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(output)
+        .with(tracer.map(otel_layer))
+        .with(EnvFilter::try_new(filter)?)
+        .try_init()
+        .map_err(|e| LoggingError::Install(e.to_string()))
+}
@@ -0,0 +1,146 @@
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use temporalio_common::telemetry::{
+    HistogramBucketOverrides, PrometheusExporterOptions,
+    metrics::{
+        Counter, HistogramDuration, HistogramF64, MetricAttributes, MetricKeyValue,
+        MetricParameters, NewAttributes, TemporalMeter,
+    },
+};
+
+use tracing::warn;
+
+use crate::banking_client::BankingError;
+use crate::search_attributes::TransferStatus;
+
+pub const TRANSFERS: &str = "money_transfer_transfers";
+pub const TRANSFER_AMOUNT: &str = "money_transfer_amount";
+pub const BANK_CALL_LATENCY: &str = "money_transfer_bank_call_latency";
+pub const BANK_CALL_ERRORS: &str = "money_transfer_bank_call_errors";
+
+/// Bucket boundaries for [`TRANSFER_AMOUNT`], in the transfer's currency.
+pub const AMOUNT_BUCKETS: [f64; 9] = [
+    10.0, 50.0, 100.0, 500.0, 1_000.0, 5_000.0, 10_000.0, 50_000.0, 100_000.0,
+];
+
+struct TransferMetrics {
+    meter: TemporalMeter,
+    transfers: Counter,
+    transfer_amount: HistogramF64,
+    bank_call_latency: HistogramDuration,
+    bank_call_errors: Counter,
+}
+
+static METRICS: OnceLock<TransferMetrics> = OnceLock::new();
+
+/// Options for a Prometheus endpoint on `address` that serves the SDK's metrics and ours
+/// at `/metrics`.
+pub fn prometheus_options(address: SocketAddr) -> PrometheusExporterOptions {
+    PrometheusExporterOptions::builder()
+        .socket_addr(address)
+        .histogram_bucket_overrides(HistogramBucketOverrides {
+            overrides: [(TRANSFER_AMOUNT.to_string(), AMOUNT_BUCKETS.to_vec())].into(),
+        })
+        .build()
+}
+
+/// Record the transfer metrics through `meter` from now on.
+///
+/// Until this is called, and in any process that never calls it (the starter, tests),
+/// every recording below does nothing. Only the first call has any effect.
+pub fn install(meter: TemporalMeter) {
+    METRICS.get_or_init(|| TransferMetrics {
+        transfers: meter.counter(
+            MetricParameters::builder()
+                .name(TRANSFERS)
+                .description("Money transfers that have finished, by outcome")
+                .build(),
+        ),
+        transfer_amount: meter.histogram_f64(
+            MetricParameters::builder()
+                .name(TRANSFER_AMOUNT)
+                .description("Amounts of finished money transfers, by outcome")
+                .build(),
+        ),
+        bank_call_latency: meter.histogram_duration(
+            MetricParameters::builder()
+                .name(BANK_CALL_LATENCY)
+                .description("Time taken by calls to the bank, by operation")
+                .build(),
+        ),
+        bank_call_errors: meter.counter(
+            MetricParameters::builder()
+                .name(BANK_CALL_ERRORS)
+                .description("Calls to the bank that returned an error, by operation and error")
+                .build(),
+        ),
+        meter,
+    });
+}
+
+/// Count a finished transfer and its amount under `outcome`.
+///
+/// Call from workflow code only when not replaying, so a replay doesn't count it again.
+/// That still counts at least once rather than exactly once: when the workflow task that
+/// records it fails or times out before the server gets its completion, the task runs
+/// live again on the next attempt and records the transfer a second time.
+pub fn record_transfer(outcome: TransferStatus, amount: Decimal) {
+    let Some(metrics) = METRICS.get() else {
+        return;
+    };
+    let attributes = metrics.attributes([MetricKeyValue::new("outcome", outcome.as_str())]);
+    metrics.transfers.add(1, &attributes);
+    metrics
+        .transfer_amount
+        .record(amount.to_f64().unwrap_or_default(), &attributes);
+}
+
+/// Run a call to the bank, recording how long it took and whether it failed, and logging
+/// the failure.
+pub fn time_bank_call<T>(
+    operation: &'static str,
+    call: impl FnOnce() -> Result<T, BankingError>,
+) -> Result<T, BankingError> {
+    let started = Instant::now();
+    let result = call();
+    if let Err(error) = &result {
+        warn!(operation, %error, "bank call failed");
+    }
+    if let Some(metrics) = METRICS.get() {
+        metrics.record_bank_call(operation, started.elapsed(), result.as_ref().err());
+    }
+    result
+}
+
+impl TransferMetrics {
+    fn attributes(&self, labels: impl IntoIterator<Item = MetricKeyValue>) -> MetricAttributes {
+        self.meter.extend_attributes(
+            self.meter.get_default_attributes().clone(),
+            NewAttributes::from(labels),
+        )
+    }
+
+    fn record_bank_call(
+        &self,
+        operation: &'static str,
+        elapsed: Duration,
+        error: Option<&BankingError>,
+    ) {
+        let attributes = self.attributes([MetricKeyValue::new("operation", operation)]);
+        self.bank_call_latency.record(elapsed, &attributes);
+        if let Some(error) = error {
+            let kind = match error {
+                BankingError::InsufficientFunds(_) => "insufficient_funds",
+                BankingError::InvalidAccount(_) => "invalid_account",
+            };
+            let attributes = self.meter.extend_attributes(
+                attributes,
+                NewAttributes::from([MetricKeyValue::new("error", kind)]),
+            );
+            self.bank_call_errors.add(1, &attributes);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+/// Replace `field` with `value` when `value` is set, so a flag or environment variable
+/// overrides the config file only when it was given.
+pub(crate) fn set<T: Clone>(field: &mut Option<T>, value: &Option<T>) {
+    if value.is_some() {
+        field.clone_from(value);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use temporalio_common::protos::{
+    coresdk::{AsJsonPayloadExt, FromJsonPayloadExt},
+    temporal::api::common::v1::Payload,
+};
+
+use crate::shared::PaymentDetails;
+
+// ---------------------------------------------------------------------------
+// Error types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown transfer status {0:?}")]
+pub struct UnknownStatusError(pub String);
+
+// ---------------------------------------------------------------------------
+// Search attribute names
+// ---------------------------------------------------------------------------
+
+pub const TRANSFER_AMOUNT: &str = "TransferAmount";
+pub const TRANSFER_SOURCE_ACCOUNT: &str = "TransferSourceAccount";
+pub const TRANSFER_TARGET_ACCOUNT: &str = "TransferTargetAccount";
+pub const TRANSFER_STATUS: &str = "TransferStatus";
+pub const TRANSFER_REFERENCE_ID: &str = "TransferReferenceId";
+
+/// Every custom search attribute with its indexed type. These must be registered on
+/// the namespace before a transfer is started.
+pub const SEARCH_ATTRIBUTES: [(&str, &str); 5] = [
+    (TRANSFER_AMOUNT, "Double"),
+    (TRANSFER_SOURCE_ACCOUNT, "Keyword"),
+    (TRANSFER_TARGET_ACCOUNT, "Keyword"),
+    (TRANSFER_STATUS, "Keyword"),
+    (TRANSFER_REFERENCE_ID, "Keyword"),
+];
+
+/// Memo field holding the [`PaymentDetails`] the transfer was started with.
+pub const PAYMENT_DETAILS_MEMO: &str = "PaymentDetails";
+
+/// Where a transfer is up to, as recorded in the `TransferStatus` search attribute.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransferStatus {
+    #[default]
+    Started,
+    /// Over the approval threshold and waiting for an `approve` or `reject` signal.
+    AwaitingApproval,
+    Withdrawn,
+    Deposited,
+    Completed,
+    Failed,
+    /// Failed after the withdrawal, which was then refunded.
+    Compensated,
+    Cancelled,
+    /// Turned down by an approver before any money moved.
+    Rejected,
+}
+
+impl TransferStatus {
+    pub const ALL: [TransferStatus; 9] = [
+        TransferStatus::Started,
+        TransferStatus::AwaitingApproval,
+        TransferStatus::Withdrawn,
+        TransferStatus::Deposited,
+        TransferStatus::Completed,
+        TransferStatus::Failed,
+        TransferStatus::Compensated,
+        TransferStatus::Cancelled,
+        TransferStatus::Rejected,
+    ];
+
+    /// The keyword stored in the search attribute.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransferStatus::Started => "Started",
+            TransferStatus::AwaitingApproval => "AwaitingApproval",
+            TransferStatus::Withdrawn => "Withdrawn",
+            TransferStatus::Deposited => "Deposited",
+            TransferStatus::Completed => "Completed",
+            TransferStatus::Failed => "Failed",
+            TransferStatus::Compensated => "Compensated",
+            TransferStatus::Cancelled => "Cancelled",
+            TransferStatus::Rejected => "Rejected",
+        }
+    }
+}
+
+impl std::fmt::Display for TransferStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransferStatus {
+    type Err = UnknownStatusError;
+
+    /// Parse a status name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TransferStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownStatusError(s.to_string()))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Payload helpers
+// ---------------------------------------------------------------------------
+
+fn typed_payload(value: impl Serialize, indexed_type: &str) -> Payload {
+    let mut payload = value
+        .as_json_payload()
+        .expect("search attribute values serialize to JSON");
+    payload
+        .metadata
+        .insert("type".to_string(), indexed_type.as_bytes().to_vec());
+    payload
+}
+
+fn keyword(value: impl Serialize) -> Payload {
+    typed_payload(value, "Keyword")
+}
+
+/// The search attributes a transfer is started with.
+pub fn transfer_search_attributes(details: &PaymentDetails) -> HashMap<String, Payload> {
+    HashMap::from([
+        (
+            TRANSFER_AMOUNT.to_string(),
+            typed_payload(details.amount.to_f64().unwrap_or_default(), "Double"),
+        ),
+        (
+            TRANSFER_SOURCE_ACCOUNT.to_string(),
+            keyword(&details.source_account),
+        ),
+        (
+            TRANSFER_TARGET_ACCOUNT.to_string(),
+            keyword(&details.target_account),
+        ),
+        (
+            TRANSFER_STATUS.to_string(),
+            keyword(TransferStatus::Started),
+        ),
+        (
+            TRANSFER_REFERENCE_ID.to_string(),
+            keyword(&details.reference_id),
+        ),
+    ])
+}
+
+/// The upsert recording a new transfer status.
+pub fn status_search_attribute(status: TransferStatus) -> (String, Payload) {
+    (TRANSFER_STATUS.to_string(), keyword(status))
+}
+
+/// Read the `TransferStatus` back out of a workflow's indexed search attributes.
+pub fn transfer_status(search_attributes: &HashMap<String, Payload>) -> Option<TransferStatus> {
+    search_attributes
+        .get(TRANSFER_STATUS)
+        .and_then(|payload| TransferStatus::from_json_payload(payload).ok())
+}
+
+/// The memo attached to a transfer, so its inputs show up when describing the workflow.
+pub fn transfer_memo(details: &PaymentDetails) -> (String, Payload) {
+    (
+        PAYMENT_DETAILS_MEMO.to_string(),
+        details
+            .as_json_payload()
+            .expect("payment details serialize to JSON"),
+    )
+}
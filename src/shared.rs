@@ -1,8 +1,25 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::fees::FeeBreakdown;
+
 pub const MONEY_TRANSFER_TASK_QUEUE_NAME: &str = "TRANSFER_MONEY_TASK_QUEUE";
 
+/// Most decimal places a transfer amount may carry.
+pub const MAX_AMOUNT_SCALE: u32 = 2;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidPaymentError {
+    #[error("account number {0:?} is not in the form NN-NNN")]
+    MalformedAccount(String),
+    #[error("amount must be positive, got {0}")]
+    NonPositiveAmount(Decimal),
+    #[error("amount {0} has more than {MAX_AMOUNT_SCALE} decimal places")]
+    AmountScale(Decimal),
+    #[error("source and target are both account {0}")]
+    SameAccount(String),
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct PaymentDetails {
     pub amount: Decimal,
@@ -10,3 +27,115 @@ pub struct PaymentDetails {
     pub target_account: String,
     pub reference_id: String,
 }
+
+impl PaymentDetails {
+    /// The workflow id a transfer is started under, derived from its reference id so a
+    /// retried submission can't start a second transfer.
+    pub fn workflow_id(&self) -> String {
+        format!("transfer-{}", self.reference_id)
+    }
+
+    /// Check the details are well-formed before any bank is contacted.
+    pub fn validate(&self) -> Result<(), InvalidPaymentError> {
+        for account in [&self.source_account, &self.target_account] {
+            if !is_account_number(account) {
+                return Err(InvalidPaymentError::MalformedAccount(account.clone()));
+            }
+        }
+        if self.amount <= Decimal::ZERO {
+            return Err(InvalidPaymentError::NonPositiveAmount(self.amount));
+        }
+        if self.amount.normalize().scale() > MAX_AMOUNT_SCALE {
+            return Err(InvalidPaymentError::AmountScale(self.amount));
+        }
+        if self.source_account == self.target_account {
+            return Err(InvalidPaymentError::SameAccount(
+                self.source_account.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Account numbers look like `85-150`: two digits, a dash, three digits.
+fn is_account_number(account: &str) -> bool {
+    match account.split_once('-') {
+        Some((branch, number)) => {
+            branch.len() == 2
+                && number.len() == 3
+                && branch
+                    .chars()
+                    .chain(number.chars())
+                    .all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// The outcome of a completed `MoneyTransferWorkflow`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TransferResult {
+    pub withdraw_transaction_id: String,
+    pub deposit_transaction_id: String,
+    /// Unset when the transfer was free.
+    pub fee_transaction_id: Option<String>,
+    pub principal: Decimal,
+    /// Principal plus fee, taken from the source account in a single withdrawal.
+    pub total_debited: Decimal,
+    pub fee: FeeBreakdown,
+    /// Who approved the transfer, when it was large enough to need approval.
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    /// The reason given by a cancellation request that arrived after the deposit, too late
+    /// to stop the transfer.
+    #[serde(default)]
+    pub late_cancellation: Option<String>,
+}
+
+impl std::fmt::Display for TransferResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Transfer complete (transaction IDs: {}, {}",
+            self.withdraw_transaction_id, self.deposit_transaction_id
+        )?;
+        if let Some(fee_transaction_id) = &self.fee_transaction_id {
+            write!(f, ", {fee_transaction_id}")?;
+        }
+        write!(
+            f,
+            "); fee {} {}, total debited {}",
+            self.fee.fee, self.fee.currency, self.total_debited
+        )?;
+        if self.late_cancellation.is_some() {
+            write!(f, "; cancellation arrived too late to stop it")?;
+        }
+        Ok(())
+    }
+}
+
+/// A transfer routed through one or more intermediary accounts, e.g.
+/// source → clearing account → correspondent → target.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RoutedPaymentDetails {
+    pub amount: Decimal,
+    /// Every account the funds pass through, source first and target last.
+    pub route: Vec<String>,
+    pub reference_id: String,
+}
+
+impl RoutedPaymentDetails {
+    /// Split the route into one withdraw/deposit leg per hop, each with its own reference id.
+    pub fn legs(&self) -> Vec<PaymentDetails> {
+        self.route
+            .windows(2)
+            .enumerate()
+            .map(|(i, hop)| PaymentDetails {
+                amount: self.amount,
+                source_account: hop[0].clone(),
+                target_account: hop[1].clone(),
+                reference_id: format!("{}-leg-{}", self.reference_id, i + 1),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use temporalio_client::Client;
+use temporalio_sdk_core::{CoreRuntime, RuntimeOptions};
+use tokio::time::{MissedTickBehavior, interval};
+
+use money_transfer_project_template_rust::bulk::{
+    ImportFormat, ImportRow, RowResult, RowStatus, Summary, default_results_path, parse_transfers,
+    write_results,
+};
+use money_transfer_project_template_rust::starter::IdPolicyArgs;
+
+use crate::Cli;
+use crate::transfer;
+
+#[derive(clap::Args)]
+pub struct ImportArgs {
+    /// CSV (`amount,source_account,target_account,reference_id`) or JSON-lines file.
+    file: PathBuf,
+
+    /// File format; guessed from the extension when omitted.
+    #[arg(long, value_name = "csv|jsonl")]
+    format: Option<ImportFormat>,
+
+    /// Most transfers to start per second.
+    #[arg(long, env = "TRANSFER_IMPORT_RATE", default_value_t = 10.0)]
+    rate: f64,
+
+    /// Where to write one JSON line per input row; defaults to `<file>.results.jsonl`.
+    #[arg(long)]
+    results: Option<PathBuf>,
+
+    #[command(flatten)]
+    id_policy: IdPolicyArgs,
+}
+
+/// Validate the whole file, then start the valid rows at no more than `--rate` per second.
+///
+/// Exits with [`Summary::exit_code`].
+pub async fn run(cli: &Cli, args: &ImportArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let format = match args.format {
+        Some(format) => format,
+        None => ImportFormat::from_path(&args.file)
+            .map_err(|e| format!("{e}; pass --format to say how to read the file"))?,
+    };
+    if args.rate.is_nan() || args.rate <= 0.0 {
+        return Err("--rate must be positive".into());
+    }
+    let contents = std::fs::read_to_string(&args.file)?;
+
+    let (rows, invalid) = parse_transfers(&contents, format);
+    let mut results: Vec<RowResult> = invalid
+        .into_iter()
+        .map(|e| {
+            if !cli.json {
+                eprintln!("skipping {e}");
+            }
+            RowResult::invalid(e)
+        })
+        .collect();
+
+    let _runtime = CoreRuntime::new_assume_tokio(RuntimeOptions::builder().build()?)?;
+    let client = cli.connect().await?;
+
+    let mut ticks = interval(Duration::from_secs_f64(1.0 / args.rate));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    for row in rows {
+        ticks.tick().await;
+        let result = start_row(&client, &cli.task_queue, &args.id_policy, row).await;
+        if !cli.json {
+            let workflow_id = result.workflow_id.as_deref().unwrap_or_default();
+            match (&result.status, &result.error) {
+                (RowStatus::AlreadyStarted, _) => {
+                    println!("line {}: {workflow_id} already started", result.line)
+                }
+                (_, Some(error)) => {
+                    eprintln!(
+                        "line {}: {workflow_id} failed to start: {error}",
+                        result.line
+                    )
+                }
+                _ => println!("line {}: {workflow_id} started", result.line),
+            }
+        }
+        results.push(result);
+    }
+    results.sort_by_key(|r| r.line);
+
+    let path = args
+        .results
+        .clone()
+        .unwrap_or_else(|| default_results_path(&args.file));
+    write_results(
+        &results,
+        std::io::BufWriter::new(std::fs::File::create(&path)?),
+    )?;
+    let summary = Summary::tally(&results, path);
+
+    if cli.json {
+        println!("{}", serde_json::json!(summary));
+    } else {
+        println!("{summary}");
+    }
+
+    Ok(summary.exit_code())
+}
+
+async fn start_row(
+    client: &Client,
+    task_queue: &str,
+    id_policy: &IdPolicyArgs,
+    row: ImportRow,
+) -> RowResult {
+    let workflow_id = row.details.workflow_id();
+    let mut result = RowResult::started(&row);
+
+    match transfer::start_workflow(
+        client,
+        task_queue,
+        &workflow_id,
+        &row.details,
+        id_policy,
+        None,
+    )
+    .await
+    {
+        Ok(started) => {
+            if started.attached {
+                result.status = RowStatus::AlreadyStarted;
+            }
+            result.run_id = Some(started.run_id);
+        }
+        Err(e) => {
+            result.status = RowStatus::Failed;
+            result.error = Some(e.to_string());
+        }
+    }
+    result
+}
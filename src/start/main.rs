@@ -1,44 +1,151 @@
-use rust_decimal::Decimal;
-use std::str::FromStr;
-use temporalio_client::{
-    Client, ClientOptions, Connection, ConnectionOptions, WorkflowStartOptions,
-};
-use temporalio_sdk_core::{CoreRuntime, RuntimeOptions, Url};
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+use temporalio_client::Client;
+use temporalio_sdk_core::{CoreRuntime, RuntimeOptions};
+
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::layer::SubscriberExt;
 
-use money_transfer_project_template_rust::shared::{
-    MONEY_TRANSFER_TASK_QUEUE_NAME, PaymentDetails,
+use money_transfer_project_template_rust::{
+    connection::ConnectionArgs,
+    shared::MONEY_TRANSFER_TASK_QUEUE_NAME,
+    starter::TransferArgs,
+    trace_context::{otel_layer, otlp_tracer_provider},
 };
-use money_transfer_project_template_rust::workflow::MoneyTransferWorkflow;
+
+mod import;
+mod manage;
+mod transfer;
+mod watch;
+
+/// Start money transfer workflows.
+///
+/// Without a subcommand, starts a single transfer. Every flag can also be set through the
+/// environment variable shown next to it.
+#[derive(Parser)]
+#[command(name = "start", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    transfer: TransferArgs,
+
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    /// Task queue new transfers are started on; must match the worker's.
+    #[arg(long, global = true, env = "TEMPORAL_TASK_QUEUE", default_value = MONEY_TRANSFER_TASK_QUEUE_NAME)]
+    task_queue: String,
+
+    /// Export a trace of each started transfer over OTLP/gRPC to this collector.
+    #[arg(long, global = true, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
+    /// Print a single JSON object instead of human-readable output.
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start every transfer in a CSV or JSON-lines file.
+    Import(import::ImportArgs),
+    /// Show a transfer's execution status, transfer status and payment details.
+    Describe(manage::WorkflowIdArg),
+    /// Ask a running transfer where it is up to.
+    Status(manage::WorkflowIdArg),
+    /// Approve a transfer that is awaiting approval; fails if it isn't.
+    Approve(manage::ApproveArgs),
+    /// Reject a transfer that is awaiting approval; fails if it isn't.
+    Reject(manage::ReasonArgs),
+    /// Cancel a transfer, refunding any withdrawal it has made.
+    Cancel(manage::ReasonArgs),
+    /// Terminate a transfer immediately, without any refund.
+    Terminate(manage::ReasonArgs),
+    /// List recent transfers.
+    List(manage::ListArgs),
+    /// Follow a transfer's history as it happens, until the workflow closes.
+    Watch(manage::WorkflowIdArg),
+}
+
+impl Cli {
+    async fn connect(&self) -> Result<Client, Box<dyn std::error::Error>> {
+        self.connection.config()?.connect("rust-client").await
+    }
+
+    /// Start exporting spans if `--otlp-endpoint` is set. Nothing is logged; the spans
+    /// only go to the collector.
+    fn init_tracing(&self) -> Result<Option<SdkTracerProvider>, Box<dyn std::error::Error>> {
+        let Some(endpoint) = &self.otlp_endpoint else {
+            return Ok(None);
+        };
+        let provider = otlp_tracer_provider(endpoint, "money-transfer-starter")?;
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry().with(otel_layer(&provider)),
+        )?;
+        Ok(Some(provider))
+    }
+
+    fn report_error(&self, error: &dyn std::error::Error) {
+        if self.json {
+            println!("{}", serde_json::json!({ "error": error.to_string() }));
+        } else {
+            eprintln!("error: {error}");
+        }
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _runtime = CoreRuntime::new_assume_tokio(RuntimeOptions::builder().build()?)?;
-    let connection = Connection::connect(
-        ConnectionOptions::new(Url::from_str("http://localhost:7233")?)
-            .identity("rust-client".to_string())
-            .build(),
-    )
-    .await?;
-    let client = Client::new(connection, ClientOptions::new("default").build())?;
-
-    let payment = PaymentDetails {
-        amount: Decimal::new(400, 2), // 4.00
-        source_account: "85-150".to_string(),
-        target_account: "43-812".to_string(),
-        reference_id: "12345".to_string(),
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let tracer = match cli.init_tracing() {
+        Ok(tracer) => tracer,
+        Err(e) => {
+            cli.report_error(e.as_ref());
+            return ExitCode::FAILURE;
+        }
     };
+    let result = match &cli.command {
+        None => start_transfer(&cli).await,
+        Some(Command::Import(args)) => import::run(&cli, args).await,
+        Some(Command::Describe(args)) => manage::describe(&cli, args).await,
+        Some(Command::Status(args)) => manage::status(&cli, args).await,
+        Some(Command::Approve(args)) => manage::approve(&cli, args).await,
+        Some(Command::Reject(args)) => manage::reject(&cli, args).await,
+        Some(Command::Cancel(args)) => manage::cancel(&cli, args).await,
+        Some(Command::Terminate(args)) => manage::terminate(&cli, args).await,
+        Some(Command::List(args)) => manage::list(&cli, args).await,
+        Some(Command::Watch(args)) => watch::run(&cli, args).await,
+    };
+    if let Some(tracer) = tracer
+        && let Err(e) = tracer.shutdown()
+    {
+        eprintln!("warning: couldn't export the trace: {e}");
+    }
+    result.unwrap_or_else(|e| {
+        cli.report_error(e.as_ref());
+        ExitCode::FAILURE
+    })
+}
 
-    let workflow_id = "pay-invoice-701".to_string();
-    let options =
-        WorkflowStartOptions::new(MONEY_TRANSFER_TASK_QUEUE_NAME, workflow_id.clone()).build();
-
-    let handle = client
-        .start_workflow(MoneyTransferWorkflow, payment, options)
-        .await?;
+async fn start_transfer(cli: &Cli) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // Invalid input exits with the same code clap uses for bad arguments.
+    let payment = match cli.transfer.payment() {
+        Ok(payment) => payment,
+        Err(e) => {
+            cli.report_error(e.as_ref());
+            return Ok(ExitCode::from(2));
+        }
+    };
 
-    println!(
-        "Started workflow {workflow_id}, run_id: {}",
-        handle.run_id().unwrap_or("<unknown>")
-    );
-    Ok(())
+    let _runtime = CoreRuntime::new_assume_tokio(RuntimeOptions::builder().build()?)?;
+    let client = cli.connect().await?;
+    let report = transfer::start(&client, &cli.task_queue, &cli.transfer, payment).await?;
+    if cli.json {
+        println!("{}", serde_json::json!(report));
+    } else {
+        println!("{report}");
+    }
+    Ok(report.outcome.exit_code())
 }
@@ -0,0 +1,308 @@
+use futures::TryStreamExt;
+use serde::Serialize;
+use std::process::ExitCode;
+use std::time::SystemTime;
+use temporalio_client::{
+    Client, WorkflowCancelOptions, WorkflowDescribeOptions, WorkflowExecuteUpdateOptions,
+    WorkflowExecution, WorkflowHandle, WorkflowListOptions, WorkflowQueryOptions,
+    WorkflowTerminateOptions, errors::WorkflowUpdateError,
+};
+use temporalio_common::{WorkflowDefinition, protos::coresdk::FromJsonPayloadExt};
+use temporalio_sdk_core::{CoreRuntime, RuntimeOptions};
+
+use money_transfer_project_template_rust::search_attributes::{
+    PAYMENT_DETAILS_MEMO, TRANSFER_STATUS, TransferStatus, transfer_status,
+};
+use money_transfer_project_template_rust::shared::PaymentDetails;
+use money_transfer_project_template_rust::workflow::MoneyTransferWorkflow;
+
+use crate::Cli;
+
+#[derive(clap::Args)]
+pub struct WorkflowIdArg {
+    /// Id of the transfer's workflow.
+    pub workflow_id: String,
+}
+
+#[derive(clap::Args)]
+pub struct ApproveArgs {
+    #[command(flatten)]
+    id: WorkflowIdArg,
+
+    /// Who is approving the transfer; recorded in its result.
+    #[arg(long, env = "USER", default_value = "ops")]
+    approver: String,
+}
+
+#[derive(clap::Args)]
+pub struct ReasonArgs {
+    #[command(flatten)]
+    id: WorkflowIdArg,
+
+    /// Why; recorded in the workflow's history.
+    #[arg(long)]
+    reason: String,
+}
+
+#[derive(clap::Args)]
+pub struct ListArgs {
+    /// Only list transfers with this status, e.g. AwaitingApproval.
+    #[arg(long)]
+    status: Option<TransferStatus>,
+
+    /// Most transfers to list.
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+/// A transfer as shown by `describe` and `list`.
+#[derive(Serialize)]
+struct TransferSummary {
+    workflow_id: String,
+    run_id: String,
+    /// Temporal's view of the execution: Running, Completed, Failed, ...
+    execution_status: String,
+    /// The workflow's own view, from the `TransferStatus` search attribute.
+    transfer_status: Option<TransferStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payment: Option<PaymentDetails>,
+    start_time: Option<String>,
+    close_time: Option<String>,
+}
+
+impl TransferSummary {
+    fn new(execution: &WorkflowExecution) -> Self {
+        let payment = execution
+            .memo()
+            .and_then(|memo| memo.fields.get(PAYMENT_DETAILS_MEMO))
+            .and_then(|payload| PaymentDetails::from_json_payload(payload).ok());
+        Self {
+            workflow_id: execution.id().to_string(),
+            run_id: execution.run_id().to_string(),
+            execution_status: format!("{:?}", execution.status()),
+            transfer_status: execution
+                .search_attributes()
+                .and_then(|attributes| transfer_status(&attributes.indexed_fields)),
+            payment,
+            start_time: execution.start_time().map(timestamp),
+            close_time: execution.close_time().map(timestamp),
+        }
+    }
+}
+
+impl std::fmt::Display for TransferSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let transfer_status = self
+            .transfer_status
+            .map_or("-".to_string(), |status| status.to_string());
+        write!(
+            f,
+            "{}  {}  {transfer_status}",
+            self.workflow_id, self.execution_status
+        )?;
+        if let Some(payment) = &self.payment {
+            write!(
+                f,
+                "  {} {} -> {}",
+                payment.amount, payment.source_account, payment.target_account
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn timestamp(time: SystemTime) -> String {
+    prost_wkt_types::Timestamp::from(time).to_string()
+}
+
+/// Handle to an existing transfer. The workflow's definition types can't be named outside
+/// the library, so the handle's type is taken from `MoneyTransferWorkflow::run`.
+fn transfer_handle<W: WorkflowDefinition>(
+    client: &Client,
+    workflow_id: &str,
+    _workflow: W,
+) -> WorkflowHandle<Client, W> {
+    client.get_workflow_handle(workflow_id)
+}
+
+/// Print `human` normally, or `json` with `--json`.
+fn report(cli: &Cli, human: impl std::fmt::Display, json: serde_json::Value) {
+    if cli.json {
+        println!("{json}");
+    } else {
+        println!("{human}");
+    }
+}
+
+pub async fn connect(cli: &Cli) -> Result<(CoreRuntime, Client), Box<dyn std::error::Error>> {
+    let runtime = CoreRuntime::new_assume_tokio(RuntimeOptions::builder().build()?)?;
+    let client = cli.connect().await?;
+    Ok((runtime, client))
+}
+
+pub async fn describe(
+    cli: &Cli,
+    args: &WorkflowIdArg,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    let description = transfer_handle(&client, &args.workflow_id, MoneyTransferWorkflow::run)
+        .describe(WorkflowDescribeOptions::default())
+        .await?;
+    let info = description
+        .raw_description
+        .workflow_execution_info
+        .ok_or("the server returned no execution info")?;
+    let summary = TransferSummary::new(&WorkflowExecution::new(info));
+
+    let mut human = summary.to_string();
+    for (label, time) in [
+        ("started", &summary.start_time),
+        ("closed", &summary.close_time),
+    ] {
+        if let Some(time) = time {
+            human.push_str(&format!("\n  {label} {time}"));
+        }
+    }
+    report(cli, human, serde_json::json!(summary));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Ask the running workflow where it is up to. Unlike `describe`, this needs a worker.
+pub async fn status(
+    cli: &Cli,
+    args: &WorkflowIdArg,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    let status = transfer_handle(&client, &args.workflow_id, MoneyTransferWorkflow::run)
+        .query(
+            MoneyTransferWorkflow::status,
+            (),
+            WorkflowQueryOptions::default(),
+        )
+        .await?;
+    report(
+        cli,
+        format!("{}: {status}", args.workflow_id),
+        serde_json::json!({ "workflow_id": args.workflow_id, "status": status }),
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Approve a transfer that is waiting for a decision. Fails if it isn't, e.g. because it
+/// is below the approval threshold, already decided or already over.
+pub async fn approve(
+    cli: &Cli,
+    args: &ApproveArgs,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    let approver = transfer_handle(&client, &args.id.workflow_id, MoneyTransferWorkflow::run)
+        .execute_update(
+            MoneyTransferWorkflow::approve_pending,
+            args.approver.clone(),
+            WorkflowExecuteUpdateOptions::default(),
+        )
+        .await
+        .map_err(decision_refused)?;
+    report(
+        cli,
+        format!("Approved {} as {approver}", args.id.workflow_id),
+        serde_json::json!({ "workflow_id": args.id.workflow_id, "approved_by": approver }),
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reject a transfer that is waiting for a decision. Fails if it isn't.
+pub async fn reject(cli: &Cli, args: &ReasonArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    let reason = transfer_handle(&client, &args.id.workflow_id, MoneyTransferWorkflow::run)
+        .execute_update(
+            MoneyTransferWorkflow::reject_pending,
+            args.reason.clone(),
+            WorkflowExecuteUpdateOptions::default(),
+        )
+        .await
+        .map_err(decision_refused)?;
+    report(
+        cli,
+        format!("Rejected {}", args.id.workflow_id),
+        serde_json::json!({ "workflow_id": args.id.workflow_id, "rejected": reason }),
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Say why the workflow turned a decision down, rather than dumping the whole failure.
+fn decision_refused(error: WorkflowUpdateError) -> Box<dyn std::error::Error> {
+    match error {
+        WorkflowUpdateError::Failed(failure) => {
+            format!("the transfer refused the decision: {}", failure.message).into()
+        }
+        other => other.into(),
+    }
+}
+
+/// Ask the transfer to stop. It refunds any withdrawal it has already made.
+pub async fn cancel(cli: &Cli, args: &ReasonArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    transfer_handle(&client, &args.id.workflow_id, MoneyTransferWorkflow::run)
+        .cancel(
+            WorkflowCancelOptions::builder()
+                .reason(args.reason.clone())
+                .build(),
+        )
+        .await?;
+    report(
+        cli,
+        format!("Requested cancellation of {}", args.id.workflow_id),
+        serde_json::json!({ "workflow_id": args.id.workflow_id, "cancel_requested": args.reason }),
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Stop the transfer immediately. Nothing is refunded, so check `describe` afterwards.
+pub async fn terminate(
+    cli: &Cli,
+    args: &ReasonArgs,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    transfer_handle(&client, &args.id.workflow_id, MoneyTransferWorkflow::run)
+        .terminate(
+            WorkflowTerminateOptions::builder()
+                .reason(args.reason.clone())
+                .build(),
+        )
+        .await?;
+    report(
+        cli,
+        format!("Terminated {}", args.id.workflow_id),
+        serde_json::json!({ "workflow_id": args.id.workflow_id, "terminated": args.reason }),
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+/// List transfers, most recent first, optionally only those with one status.
+pub async fn list(cli: &Cli, args: &ListArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = connect(cli).await?;
+    let mut query = format!("WorkflowType = '{}'", MoneyTransferWorkflow::name());
+    if let Some(status) = args.status {
+        query.push_str(&format!(" AND {TRANSFER_STATUS} = '{status}'"));
+    }
+    let transfers: Vec<TransferSummary> = client
+        .list_workflows(
+            query,
+            WorkflowListOptions::builder().limit(args.limit).build(),
+        )
+        .map_ok(|execution| TransferSummary::new(&execution))
+        .try_collect()
+        .await?;
+
+    if cli.json {
+        println!("{}", serde_json::json!(transfers));
+    } else if transfers.is_empty() {
+        println!("No transfers found");
+    } else {
+        for transfer in &transfers {
+            println!("{transfer}");
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+use temporalio_client::{
+    Client, NamespacedClient, WorkflowDescribeOptions, WorkflowExecutionInfo,
+    WorkflowGetResultOptions, WorkflowHandle,
+    errors::WorkflowGetResultError,
+    grpc::WorkflowService,
+    tonic::{Code, IntoRequest},
+};
+use temporalio_common::protos::{
+    coresdk::{AsJsonPayloadExt, IntoPayloadsExt},
+    temporal::api::{
+        common::v1::{SearchAttributes, WorkflowType},
+        taskqueue::v1::TaskQueue,
+        workflowservice::v1::StartWorkflowExecutionRequest,
+    },
+};
+use tracing::{Instrument, info_span};
+use uuid::Uuid;
+
+use money_transfer_project_template_rust::search_attributes::{
+    transfer_search_attributes, transfer_status,
+};
+use money_transfer_project_template_rust::shared::PaymentDetails;
+use money_transfer_project_template_rust::starter::{IdPolicyArgs, Outcome, Report, TransferArgs};
+use money_transfer_project_template_rust::trace_context::TraceContext;
+use money_transfer_project_template_rust::workflow::MoneyTransferWorkflow;
+
+pub async fn start(
+    client: &Client,
+    task_queue: &str,
+    args: &TransferArgs,
+    payment: PaymentDetails,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let workflow_id = args.workflow_id(&payment);
+    let started = start_workflow(
+        client,
+        task_queue,
+        &workflow_id,
+        &payment,
+        &args.id_policy,
+        args.execution_timeout.map(Duration::from_secs),
+    )
+    .await?;
+
+    let mut report = Report {
+        workflow_id: workflow_id.clone(),
+        run_id: Some(started.run_id.clone()),
+        attached: started.attached,
+        outcome: Outcome::Started,
+        result: None,
+        failure: None,
+    };
+    if !args.wait {
+        return Ok(report);
+    }
+
+    let handle: WorkflowHandle<Client, MoneyTransferWorkflow> = WorkflowHandle::new(
+        client.clone(),
+        WorkflowExecutionInfo {
+            namespace: client.namespace(),
+            workflow_id,
+            run_id: Some(started.run_id),
+            first_execution_run_id: None,
+        },
+    );
+    match handle.get_result(WorkflowGetResultOptions::default()).await {
+        Ok(result) => {
+            report.outcome = Outcome::Completed;
+            report.result = Some(result);
+        }
+        Err(WorkflowGetResultError::Failed(failure)) => {
+            let description = handle.describe(WorkflowDescribeOptions::default()).await?;
+            let status = description
+                .raw_description
+                .workflow_execution_info
+                .and_then(|info| info.search_attributes)
+                .and_then(|attributes| transfer_status(&attributes.indexed_fields));
+            report.outcome = Outcome::of_failure(status);
+            report.failure = Some(failure.message);
+        }
+        Err(WorkflowGetResultError::Terminated { .. }) => {
+            report.outcome = Outcome::Failed;
+            report.failure = Some("workflow was terminated".to_string());
+        }
+        Err(WorkflowGetResultError::Cancelled { .. }) => report.outcome = Outcome::Cancelled,
+        Err(WorkflowGetResultError::TimedOut) => report.outcome = Outcome::TimedOut,
+        Err(e) => return Err(e.into()),
+    }
+    Ok(report)
+}
+
+/// The run a start request ended up with.
+pub struct Started {
+    pub run_id: String,
+    /// A transfer was still running under the id, and the attach policy returned it
+    /// instead of starting another.
+    pub attached: bool,
+}
+
+/// Start a transfer under `workflow_id`, as the id policies say.
+///
+/// This calls the service directly because `Client::start_workflow` doesn't say whether
+/// the server started a run or handed back the open one under `UseExisting`. A closed run
+/// the reuse policy refuses to repeat is an error, whatever the conflict policy.
+pub async fn start_workflow(
+    client: &Client,
+    task_queue: &str,
+    workflow_id: &str,
+    payment: &PaymentDetails,
+    id_policy: &IdPolicyArgs,
+    execution_timeout: Option<Duration>,
+) -> Result<Started, Box<dyn std::error::Error>> {
+    let span = start_span(workflow_id);
+    let request = StartWorkflowExecutionRequest {
+        namespace: client.namespace(),
+        workflow_id: workflow_id.to_string(),
+        workflow_type: Some(WorkflowType {
+            name: MoneyTransferWorkflow::name().to_string(),
+        }),
+        task_queue: Some(TaskQueue {
+            name: task_queue.to_string(),
+            ..Default::default()
+        }),
+        input: vec![payment.as_json_payload()?].into_payloads(),
+        workflow_execution_timeout: execution_timeout.and_then(|t| t.try_into().ok()),
+        identity: client.identity(),
+        request_id: Uuid::new_v4().to_string(),
+        workflow_id_reuse_policy: id_policy.reuse_policy() as i32,
+        workflow_id_conflict_policy: id_policy.conflict_policy() as i32,
+        search_attributes: Some(SearchAttributes {
+            indexed_fields: transfer_search_attributes(payment),
+        }),
+        header: TraceContext::of(&span).header(),
+        ..Default::default()
+    };
+    let response =
+        WorkflowService::start_workflow_execution(&mut client.clone(), request.into_request())
+            .instrument(span)
+            .await
+            .map_err(|status| -> Box<dyn std::error::Error> {
+                if status.code() == Code::AlreadyExists {
+                    format!(
+                        "transfer {workflow_id} was already started: {}",
+                        status.message()
+                    )
+                    .into()
+                } else {
+                    status.into()
+                }
+            })?
+            .into_inner();
+    Ok(Started {
+        run_id: response.run_id,
+        attached: !response.started,
+    })
+}
+
+/// The root span of a transfer's trace. Its context goes to the workflow in the start
+/// header, so the workflow's activities and bank calls join the same trace.
+pub fn start_span(workflow_id: &str) -> tracing::Span {
+    info_span!(
+        "start transfer",
+        otel.name = "StartWorkflow:MoneyTransferWorkflow",
+        otel.kind = "client",
+        workflow_id,
+    )
+}
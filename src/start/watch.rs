@@ -0,0 +1,95 @@
+use std::process::ExitCode;
+use std::time::Duration;
+use temporalio_client::{NamespacedClient, grpc::WorkflowService, tonic::IntoRequest};
+use temporalio_common::protos::temporal::api::{
+    common::v1::WorkflowExecution,
+    workflowservice::v1::{DescribeWorkflowExecutionRequest, GetWorkflowExecutionHistoryRequest},
+};
+use tokio::time::{MissedTickBehavior, interval};
+
+use money_transfer_project_template_rust::timeline::{Timeline, is_close_event};
+
+use crate::Cli;
+use crate::manage::WorkflowIdArg;
+
+/// How often open activities are checked for retries while waiting on the history.
+const PENDING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Print the transfer's history so far, then each new event as it happens, until the
+/// workflow closes. With `--json`, each entry is printed as its own JSON line.
+///
+/// A retrying activity adds nothing to the history until it closes, so while waiting for
+/// new events the open activities are described too, and each new attempt is printed.
+pub async fn run(cli: &Cli, args: &WorkflowIdArg) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let (_runtime, client) = crate::manage::connect(cli).await?;
+    let execution = WorkflowExecution {
+        workflow_id: args.workflow_id.clone(),
+        run_id: String::new(),
+    };
+    let mut timeline = Timeline::new();
+    let mut next_page_token = Vec::new();
+    let mut ticks = interval(PENDING_POLL_INTERVAL);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        // With `wait_new_event` the server holds the request open until there is
+        // something new, and keeps handing back a page token while the workflow runs.
+        let mut history_client = client.clone();
+        let history = WorkflowService::get_workflow_execution_history(
+            &mut history_client,
+            GetWorkflowExecutionHistoryRequest {
+                namespace: client.namespace(),
+                execution: Some(execution.clone()),
+                next_page_token,
+                wait_new_event: true,
+                ..Default::default()
+            }
+            .into_request(),
+        );
+        tokio::pin!(history);
+        let response = loop {
+            tokio::select! {
+                response = &mut history => break response?.into_inner(),
+                _ = ticks.tick() => {
+                    let description = WorkflowService::describe_workflow_execution(
+                        &mut client.clone(),
+                        DescribeWorkflowExecutionRequest {
+                            namespace: client.namespace(),
+                            execution: Some(execution.clone()),
+                        }
+                        .into_request(),
+                    )
+                    .await?
+                    .into_inner();
+                    for activity in &description.pending_activities {
+                        if let Some(entry) = timeline.describe_pending(activity) {
+                            print_entry(cli, entry);
+                        }
+                    }
+                }
+            }
+        };
+
+        for event in response.history.map(|h| h.events).unwrap_or_default() {
+            if let Some(entry) = timeline.describe(&event) {
+                print_entry(cli, entry);
+            }
+            if is_close_event(&event) {
+                return Ok(ExitCode::SUCCESS);
+            }
+        }
+
+        if response.next_page_token.is_empty() {
+            return Ok(ExitCode::SUCCESS);
+        }
+        next_page_token = response.next_page_token;
+    }
+}
+
+fn print_entry(cli: &Cli, entry: impl std::fmt::Display + serde::Serialize) {
+    if cli.json {
+        println!("{}", serde_json::json!(entry));
+    } else {
+        println!("{entry}");
+    }
+}
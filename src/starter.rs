@@ -0,0 +1,231 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::process::ExitCode;
+use temporalio_common::protos::temporal::api::enums::v1::{
+    WorkflowIdConflictPolicy, WorkflowIdReusePolicy,
+};
+
+use crate::search_attributes::TransferStatus;
+use crate::shared::{PaymentDetails, TransferResult};
+
+/// Flags describing a single transfer.
+#[derive(clap::Args)]
+pub struct TransferArgs {
+    /// Amount to transfer, with at most two decimal places.
+    #[arg(long, env = "TRANSFER_AMOUNT", default_value = "4.00")]
+    amount: Decimal,
+
+    /// Account to withdraw from, e.g. 85-150.
+    #[arg(
+        long = "from",
+        env = "TRANSFER_SOURCE_ACCOUNT",
+        default_value = "85-150"
+    )]
+    source_account: String,
+
+    /// Account to deposit into, e.g. 43-812.
+    #[arg(long = "to", env = "TRANSFER_TARGET_ACCOUNT", default_value = "43-812")]
+    target_account: String,
+
+    /// Reference recorded against every bank transaction of the transfer.
+    #[arg(
+        long = "reference",
+        env = "TRANSFER_REFERENCE_ID",
+        default_value = "12345"
+    )]
+    reference_id: String,
+
+    /// Workflow id to start the transfer under [default: transfer-<reference>].
+    #[arg(long, env = "TRANSFER_WORKFLOW_ID")]
+    workflow_id: Option<String>,
+
+    #[command(flatten)]
+    pub id_policy: IdPolicyArgs,
+
+    /// Fail the workflow if it hasn't finished within this many seconds.
+    #[arg(long, env = "TRANSFER_EXECUTION_TIMEOUT", value_name = "SECONDS")]
+    pub execution_timeout: Option<u64>,
+
+    /// Wait for the transfer to finish and exit with a code describing the outcome.
+    #[arg(long)]
+    pub wait: bool,
+}
+
+impl TransferArgs {
+    pub fn payment(&self) -> Result<PaymentDetails, Box<dyn std::error::Error>> {
+        let payment = PaymentDetails {
+            amount: self.amount,
+            source_account: self.source_account.clone(),
+            target_account: self.target_account.clone(),
+            reference_id: self.reference_id.clone(),
+        };
+        payment.validate()?;
+        if self
+            .workflow_id
+            .as_ref()
+            .is_some_and(|id| id.trim().is_empty())
+        {
+            return Err("workflow id must not be empty".into());
+        }
+        Ok(payment)
+    }
+
+    /// The `--workflow-id`, or the id derived from the reference so that resubmitting the
+    /// same payment finds the transfer it already started.
+    pub fn workflow_id(&self, payment: &PaymentDetails) -> String {
+        self.workflow_id
+            .clone()
+            .unwrap_or_else(|| payment.workflow_id())
+    }
+}
+
+/// What to do when a transfer finished earlier under the same workflow id.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum IdReusePolicy {
+    /// Start a new transfer.
+    AllowDuplicate,
+    /// Start a new transfer only if the earlier one failed, was cancelled or was terminated.
+    AllowDuplicateFailedOnly,
+    /// Never start a second transfer under the same id.
+    RejectDuplicate,
+}
+
+/// What to do when a transfer is still running under the same workflow id.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdConflictPolicy {
+    /// Report the existing run instead of starting a new one.
+    Attach,
+    /// Treat it as an error.
+    Fail,
+    /// Terminate the running transfer and start a new one.
+    TerminateExisting,
+}
+
+/// Policies for starting a transfer whose workflow id has been used before.
+#[derive(clap::Args)]
+pub struct IdPolicyArgs {
+    /// What to do if a transfer already finished under the same workflow id.
+    #[arg(
+        long,
+        env = "TRANSFER_ID_REUSE_POLICY",
+        value_enum,
+        default_value_t = IdReusePolicy::RejectDuplicate
+    )]
+    id_reuse_policy: IdReusePolicy,
+
+    /// What to do if a transfer is still running under the same workflow id.
+    #[arg(
+        long,
+        env = "TRANSFER_ID_CONFLICT_POLICY",
+        value_enum,
+        default_value_t = IdConflictPolicy::Attach
+    )]
+    id_conflict_policy: IdConflictPolicy,
+}
+
+impl IdPolicyArgs {
+    pub fn reuse_policy(&self) -> WorkflowIdReusePolicy {
+        match self.id_reuse_policy {
+            IdReusePolicy::AllowDuplicate => WorkflowIdReusePolicy::AllowDuplicate,
+            IdReusePolicy::AllowDuplicateFailedOnly => {
+                WorkflowIdReusePolicy::AllowDuplicateFailedOnly
+            }
+            IdReusePolicy::RejectDuplicate => WorkflowIdReusePolicy::RejectDuplicate,
+        }
+    }
+
+    pub fn conflict_policy(&self) -> WorkflowIdConflictPolicy {
+        match self.id_conflict_policy {
+            IdConflictPolicy::Attach => WorkflowIdConflictPolicy::UseExisting,
+            IdConflictPolicy::Fail => WorkflowIdConflictPolicy::Fail,
+            IdConflictPolicy::TerminateExisting => WorkflowIdConflictPolicy::TerminateExisting,
+        }
+    }
+}
+
+/// How far the transfer got. With `--wait` this is also the process exit code.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Started without waiting for it to finish (exit 0).
+    Started,
+    /// Exit 0.
+    Completed,
+    /// Exit 3.
+    Failed,
+    /// Failed after the withdrawal, which was refunded (exit 4).
+    Compensated,
+    /// Exit 5.
+    Cancelled,
+    /// Exit 6.
+    TimedOut,
+}
+
+impl Outcome {
+    /// How a failed transfer ended, given the status it recorded: only the workflow knows
+    /// whether it refunded the withdrawal.
+    pub fn of_failure(status: Option<TransferStatus>) -> Self {
+        match status {
+            Some(TransferStatus::Compensated) => Outcome::Compensated,
+            _ => Outcome::Failed,
+        }
+    }
+
+    pub fn exit_code(self) -> ExitCode {
+        ExitCode::from(match self {
+            Outcome::Started | Outcome::Completed => 0,
+            Outcome::Failed => 3,
+            Outcome::Compensated => 4,
+            Outcome::Cancelled => 5,
+            Outcome::TimedOut => 6,
+        })
+    }
+}
+
+/// What the starter reports; `--json` prints it as is.
+#[derive(Serialize)]
+pub struct Report {
+    pub workflow_id: String,
+    pub run_id: Option<String>,
+    /// The transfer had already been started, and this reports on that run.
+    pub attached: bool,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<TransferResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let run_id = self.run_id.as_deref().unwrap_or("<unknown>");
+        match self.outcome {
+            Outcome::Started if self.attached => write!(
+                f,
+                "Transfer {} was already started, run_id: {run_id}",
+                self.workflow_id
+            ),
+            Outcome::Started => {
+                write!(f, "Started workflow {}, run_id: {run_id}", self.workflow_id)
+            }
+            Outcome::Completed => match &self.result {
+                Some(result) => write!(f, "{result}"),
+                None => write!(f, "Transfer {} completed", self.workflow_id),
+            },
+            Outcome::Failed => write!(
+                f,
+                "Transfer {} failed: {}",
+                self.workflow_id,
+                self.failure.as_deref().unwrap_or("unknown failure")
+            ),
+            Outcome::Compensated => write!(
+                f,
+                "Transfer {} failed and was compensated: {}",
+                self.workflow_id,
+                self.failure.as_deref().unwrap_or("unknown failure")
+            ),
+            Outcome::Cancelled => write!(f, "Transfer {} was cancelled", self.workflow_id),
+            Outcome::TimedOut => write!(f, "Transfer {} timed out", self.workflow_id),
+        }
+    }
+}
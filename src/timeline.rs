@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use temporalio_common::protos::{
+    coresdk::FromJsonPayloadExt,
+    temporal::api::{
+        common::v1::Payloads,
+        failure::v1::Failure,
+        history::v1::{HistoryEvent, history_event::Attributes},
+        workflow::v1::PendingActivityInfo,
+    },
+};
+
+use crate::search_attributes::transfer_status;
+
+/// One line of a transfer's timeline.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub event_id: i64,
+    /// RFC 3339 time the server recorded the event.
+    pub time: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} #{:<4} {}",
+            self.time.as_deref().unwrap_or("-"),
+            self.event_id,
+            self.message
+        )
+    }
+}
+
+/// An activity that is still open, as `DescribeWorkflowExecution` reports it.
+///
+/// The server only writes an activity's `ActivityTaskStarted` event, with its attempt and
+/// last failure, once the activity closes, so this is the only place a retrying activity
+/// shows up while it is stuck.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingActivityEntry {
+    pub activity_id: String,
+    pub activity_type: String,
+    pub attempt: i32,
+    /// Why the previous attempt failed.
+    pub last_failure: Option<String>,
+    /// RFC 3339 time the next attempt is due, while the activity is backing off.
+    pub next_attempt_time: Option<String>,
+}
+
+impl std::fmt::Display for PendingActivityEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<24} #-    {} attempt {}",
+            "(pending)", self.activity_type, self.attempt
+        )?;
+        match &self.next_attempt_time {
+            Some(time) => write!(f, " due at {time}")?,
+            None => write!(f, " running")?,
+        }
+        if let Some(failure) = &self.last_failure {
+            write!(f, " after: {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns a workflow's history events into readable timeline entries.
+///
+/// Activity events only carry the id of the event that scheduled them, so the timeline
+/// remembers activity names as it goes; feed it every event in order.
+#[derive(Default)]
+pub struct Timeline {
+    activities: HashMap<i64, String>,
+    /// The last entry reported for each pending activity, by activity id.
+    pending: HashMap<String, PendingActivityEntry>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describe `event`, or return `None` for bookkeeping events such as workflow tasks.
+    pub fn describe(&mut self, event: &HistoryEvent) -> Option<TimelineEntry> {
+        let message = match event.attributes.as_ref()? {
+            Attributes::WorkflowExecutionStartedEventAttributes(attrs) => {
+                let workflow_type = attrs
+                    .workflow_type
+                    .as_ref()
+                    .map_or("workflow", |t| t.name.as_str());
+                format!("{workflow_type} started")
+            }
+            Attributes::ActivityTaskScheduledEventAttributes(attrs) => {
+                let name = attrs
+                    .activity_type
+                    .as_ref()
+                    .map_or_else(|| "activity".to_string(), |t| t.name.clone());
+                self.activities.insert(event.event_id, name.clone());
+                format!("{name} scheduled")
+            }
+            Attributes::ActivityTaskStartedEventAttributes(attrs) => {
+                let name = self.activity(attrs.scheduled_event_id);
+                match &attrs.last_failure {
+                    Some(failure) if attrs.attempt > 1 => format!(
+                        "{name} retried, attempt {} after: {}",
+                        attrs.attempt,
+                        failure_message(failure)
+                    ),
+                    _ => format!("{name} started, attempt {}", attrs.attempt.max(1)),
+                }
+            }
+            Attributes::ActivityTaskCompletedEventAttributes(attrs) => {
+                format!("{} completed", self.activity(attrs.scheduled_event_id))
+            }
+            Attributes::ActivityTaskFailedEventAttributes(attrs) => format!(
+                "{} failed: {}",
+                self.activity(attrs.scheduled_event_id),
+                attrs.failure.as_ref().map_or("", failure_message)
+            ),
+            Attributes::ActivityTaskTimedOutEventAttributes(attrs) => {
+                format!("{} timed out", self.activity(attrs.scheduled_event_id))
+            }
+            Attributes::ActivityTaskCancelRequestedEventAttributes(attrs) => format!(
+                "{} cancellation requested",
+                self.activity(attrs.scheduled_event_id)
+            ),
+            Attributes::ActivityTaskCanceledEventAttributes(attrs) => {
+                format!("{} cancelled", self.activity(attrs.scheduled_event_id))
+            }
+            Attributes::MarkerRecordedEventAttributes(attrs) => match &attrs.failure {
+                Some(failure) => format!(
+                    "{} marker recorded with failure: {}",
+                    attrs.marker_name,
+                    failure_message(failure)
+                ),
+                None => format!("{} marker recorded", attrs.marker_name),
+            },
+            Attributes::WorkflowExecutionSignaledEventAttributes(attrs) => {
+                match payloads_json(attrs.input.as_ref()) {
+                    Some(input) => format!("signal {} received: {input}", attrs.signal_name),
+                    None => format!("signal {} received", attrs.signal_name),
+                }
+            }
+            Attributes::WorkflowExecutionCancelRequestedEventAttributes(attrs) => {
+                format!("cancellation requested: {}", attrs.cause)
+            }
+            Attributes::UpsertWorkflowSearchAttributesEventAttributes(attrs) => {
+                let status = attrs
+                    .search_attributes
+                    .as_ref()
+                    .and_then(|attributes| transfer_status(&attributes.indexed_fields))?;
+                format!("status {status}")
+            }
+            Attributes::WorkflowExecutionCompletedEventAttributes(_) => {
+                "workflow completed".to_string()
+            }
+            Attributes::WorkflowExecutionFailedEventAttributes(attrs) => format!(
+                "workflow failed: {}",
+                attrs.failure.as_ref().map_or("", failure_message)
+            ),
+            Attributes::WorkflowExecutionCanceledEventAttributes(_) => {
+                "workflow cancelled".to_string()
+            }
+            Attributes::WorkflowExecutionTerminatedEventAttributes(attrs) => {
+                format!("workflow terminated: {}", attrs.reason)
+            }
+            Attributes::WorkflowExecutionTimedOutEventAttributes(_) => {
+                "workflow timed out".to_string()
+            }
+            Attributes::WorkflowExecutionContinuedAsNewEventAttributes(attrs) => {
+                format!(
+                    "workflow continued as new run {}",
+                    attrs.new_execution_run_id
+                )
+            }
+            _ => return None,
+        };
+        Some(TimelineEntry {
+            event_id: event.event_id,
+            time: event.event_time.as_ref().map(ToString::to_string),
+            message,
+        })
+    }
+
+    /// Describe a pending activity, or return `None` if it hasn't changed since it was
+    /// last described.
+    pub fn describe_pending(
+        &mut self,
+        activity: &PendingActivityInfo,
+    ) -> Option<PendingActivityEntry> {
+        let entry = PendingActivityEntry {
+            activity_id: activity.activity_id.clone(),
+            activity_type: activity
+                .activity_type
+                .as_ref()
+                .map_or_else(|| "activity".to_string(), |t| t.name.clone()),
+            attempt: activity.attempt.max(1),
+            last_failure: activity
+                .last_failure
+                .as_ref()
+                .map(|failure| failure_message(failure).to_string()),
+            next_attempt_time: activity
+                .next_attempt_schedule_time
+                .as_ref()
+                .map(ToString::to_string),
+        };
+        if self.pending.get(&entry.activity_id) == Some(&entry) {
+            return None;
+        }
+        self.pending
+            .insert(entry.activity_id.clone(), entry.clone());
+        Some(entry)
+    }
+
+    fn activity(&self, scheduled_event_id: i64) -> &str {
+        self.activities
+            .get(&scheduled_event_id)
+            .map_or("activity", String::as_str)
+    }
+}
+
+/// Whether `event` closes the workflow execution.
+pub fn is_close_event(event: &HistoryEvent) -> bool {
+    matches!(
+        event.attributes,
+        Some(
+            Attributes::WorkflowExecutionCompletedEventAttributes(_)
+                | Attributes::WorkflowExecutionFailedEventAttributes(_)
+                | Attributes::WorkflowExecutionCanceledEventAttributes(_)
+                | Attributes::WorkflowExecutionTerminatedEventAttributes(_)
+                | Attributes::WorkflowExecutionTimedOutEventAttributes(_)
+                | Attributes::WorkflowExecutionContinuedAsNewEventAttributes(_)
+        )
+    )
+}
+
+/// The failure's message, or its cause's when the wrapper has none.
+fn failure_message(failure: &Failure) -> &str {
+    match &failure.cause {
+        Some(cause) if failure.message.is_empty() => failure_message(cause),
+        _ => &failure.message,
+    }
+}
+
+fn payloads_json(payloads: Option<&Payloads>) -> Option<String> {
+    let values: Vec<serde_json::Value> = payloads?
+        .payloads
+        .iter()
+        .filter_map(|payload| serde_json::Value::from_json_payload(payload).ok())
+        .collect();
+    match values.as_slice() {
+        [] => None,
+        [value] => Some(value.to_string()),
+        _ => Some(serde_json::Value::Array(values).to_string()),
+    }
+}
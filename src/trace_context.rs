@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, HashMap};
+
+use opentelemetry::{propagation::TextMapPropagator, trace::TracerProvider as _};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+use serde::{Deserialize, Serialize};
+use temporalio_common::protos::{
+    coresdk::{AsJsonPayloadExt, FromJsonPayloadExt},
+    temporal::api::common::v1::{Header, Payload},
+};
+use tracing::{Metadata, Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{Layer, filter::filter_fn, registry::LookupSpan};
+
+/// Header the trace context travels under, the same key Temporal's other SDKs use.
+pub const TRACE_HEADER: &str = "_tracer-data";
+
+/// The W3C trace context (`traceparent`, `tracestate`) of one span in a transfer's trace.
+///
+/// The starter sends it in the workflow's [`TRACE_HEADER`]. Activities can't be given
+/// headers from Rust workflow code, so the workflow passes it on to them in a [`Traced`]
+/// input instead.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct TraceContext(BTreeMap<String, String>);
+
+impl TraceContext {
+    /// The context of the current span, empty if it isn't being exported.
+    pub fn current() -> Self {
+        Self::of(&Span::current())
+    }
+
+    /// The context of `span`, empty if it isn't being exported.
+    pub fn of(span: &Span) -> Self {
+        let mut carrier = HashMap::new();
+        TraceContextPropagator::new().inject_context(&span.context(), &mut carrier);
+        Self(carrier.into_iter().collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn traceparent(&self) -> Option<&str> {
+        self.0.get("traceparent").map(String::as_str)
+    }
+
+    /// Continue this trace in `span`. Does nothing if the context is empty or `span`
+    /// isn't being exported.
+    pub fn set_as_parent_of(&self, span: &Span) {
+        if self.is_empty() {
+            return;
+        }
+        let carrier: HashMap<String, String> = self.0.clone().into_iter().collect();
+        let _ = span.set_parent(TraceContextPropagator::new().extract(&carrier));
+    }
+
+    /// A workflow start header carrying this context, or `None` if it's empty.
+    pub fn header(&self) -> Option<Header> {
+        if self.is_empty() {
+            return None;
+        }
+        let payload = self.as_json_payload().ok()?;
+        Some(Header {
+            fields: [(TRACE_HEADER.to_string(), payload)].into(),
+        })
+    }
+
+    /// The context sent in `headers`, empty if there is none.
+    pub fn from_headers(headers: &HashMap<String, Payload>) -> Self {
+        headers
+            .get(TRACE_HEADER)
+            .and_then(|payload| Self::from_json_payload(payload).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// An activity input together with the trace the activity belongs to.
+///
+/// The context sits next to the payload rather than inside it, so business types such as
+/// [`PaymentDetails`](crate::shared::PaymentDetails) never carry it and starters or import
+/// files can't set it. On the wire the payload's fields are inlined, with the context
+/// under `trace_context` when there is one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Traced<T> {
+    #[serde(flatten)]
+    pub value: T,
+    #[serde(default, skip_serializing_if = "TraceContext::is_empty")]
+    pub trace_context: TraceContext,
+}
+
+impl<T> Traced<T> {
+    pub fn new(value: T, trace_context: &TraceContext) -> Self {
+        Self {
+            value,
+            trace_context: trace_context.clone(),
+        }
+    }
+}
+
+/// A tracer provider exporting spans over OTLP/gRPC to `endpoint`, e.g.
+/// `http://localhost:4317`. Shut it down before exiting so the last spans are sent.
+pub fn otlp_tracer_provider(
+    endpoint: &str,
+    service_name: &'static str,
+) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build())
+}
+
+/// A `tracing` layer exporting our spans through `provider`.
+///
+/// The SDK's own spans (pollers, task processing) are left out, so each trace only holds
+/// the transfer's own steps.
+pub fn otel_layer<S>(provider: &SdkTracerProvider) -> impl Layer<S> + use<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("money-transfer"))
+        .with_filter(filter_fn(|metadata: &Metadata<'_>| {
+            !metadata.target().starts_with("temporal")
+        }))
+}
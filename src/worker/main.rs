@@ -1,37 +1,172 @@
-use std::str::FromStr;
-use temporalio_client::{Client, ClientOptions, Connection, ConnectionOptions};
+use clap::Parser;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::time::Duration;
+use temporalio_common::telemetry::start_prometheus_metric_exporter;
 use temporalio_sdk::{Worker, WorkerOptions};
-use temporalio_sdk_core::{CoreRuntime, RuntimeOptions, Url};
+use temporalio_sdk_core::{CoreRuntime, RuntimeOptions};
+use tracing::{info, warn};
 
 use money_transfer_project_template_rust::{
-    activity::Activities, shared::MONEY_TRANSFER_TASK_QUEUE_NAME, workflow::MoneyTransferWorkflow,
+    activity::Activities,
+    approval,
+    connection::ConnectionArgs,
+    in_flight, logging, metrics,
+    trace_context::otlp_tracer_provider,
+    worker_config::WorkerArgs,
+    workflow::{MoneyTransferWorkflow, RoutedMoneyTransferWorkflow},
 };
 
+/// How long after the grace period the worker still waits for activities the SDK has
+/// cancelled to report back, before giving up on them.
+const CANCELLATION_MARGIN: Duration = Duration::from_secs(5);
+
+/// Run the money transfer worker.
+///
+/// Every flag can also be set through the environment variable shown next to it.
+#[derive(Parser)]
+#[command(name = "worker", version)]
+struct Cli {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+
+    #[command(flatten)]
+    worker: WorkerArgs,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Starting up");
-    let runtime = CoreRuntime::new_assume_tokio(RuntimeOptions::builder().build()?)?;
+    let cli = Cli::parse();
+    let connection = cli.connection.config()?;
+    let config = cli.worker.config()?;
+    let tracer = config
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| otlp_tracer_provider(endpoint, "money-transfer-worker"))
+        .transpose()?;
+    logging::init(config.log_format(), config.log_filter(), tracer.as_ref())?;
 
-    let connection = Connection::connect(
-        ConnectionOptions::new(Url::from_str("http://localhost:7233")?)
-            .identity("rust-worker".to_string())
-            .build(),
-    )
-    .await?;
+    info!("starting up");
+    let mut runtime = CoreRuntime::new_assume_tokio(RuntimeOptions::builder().build()?)?;
 
-    println!("Connected");
+    if let Some(address) = config.metrics_address {
+        let server = start_prometheus_metric_exporter(metrics::prometheus_options(address))?;
+        runtime
+            .telemetry_mut()
+            .attach_late_init_metrics(server.meter);
+        info!(address = %server.bound_addr, "serving metrics at /metrics");
+    }
+    if let Some(meter) = runtime.telemetry().get_metric_meter() {
+        metrics::install(meter);
+    }
 
-    let client = Client::new(connection, ClientOptions::new("default").build())?;
+    let client = connection
+        .connect_with_metrics(
+            "rust-worker",
+            runtime.telemetry().get_temporal_metric_meter(),
+        )
+        .await?;
 
-    println!("Client created");
+    info!(namespace = connection.namespace(), "connected");
 
-    let worker_options = WorkerOptions::new(MONEY_TRANSFER_TASK_QUEUE_NAME)
+    let approval_policy = config.approval_policy();
+    approval::install(approval_policy);
+    info!(
+        threshold = %approval_policy.threshold,
+        timeout_secs = approval_policy.timeout.as_secs(),
+        "transfers above the threshold wait for approval"
+    );
+
+    let grace_period = config.shutdown_grace_period();
+    let worker_options = WorkerOptions::new(config.task_queue())
         .register_activities(Activities)
         .register_workflow::<MoneyTransferWorkflow>()
+        .register_workflow::<RoutedMoneyTransferWorkflow>()
+        .maybe_client_identity_override(config.identity.clone())
+        .tuner(config.tuner())
+        .workflow_task_poller_behavior(config.workflow_poller_behavior())
+        .activity_task_poller_behavior(config.activity_poller_behavior())
+        .maybe_max_worker_activities_per_second(config.activities_per_second)
+        .max_cached_workflows(config.sticky_cache_size())
+        .graceful_shutdown_period(grace_period)
         .build();
 
-    println!("Options set");
+    info!(task_queue = config.task_queue(), "polling");
+
+    let mut worker = Worker::new(&runtime, client, worker_options)?;
+    let shutdown = worker.shutdown_handle();
+    let run = worker.run();
+    tokio::pin!(run);
+
+    let signal = tokio::select! {
+        result = &mut run => {
+            result?;
+            shutdown_tracer(tracer);
+            return Ok(());
+        }
+        signal = shutdown_signal() => signal?,
+    };
 
-    Worker::new(&runtime, client, worker_options)?.run().await?;
+    // Stop polling and let in-flight tasks finish. Once the grace period is up the SDK
+    // cancels the activities that are left; ours don't heartbeat, so anything still
+    // running after the margin is abandoned and the server retries it elsewhere once
+    // its timeout expires.
+    info!(
+        signal,
+        grace_period_secs = grace_period.as_secs(),
+        "shutting down, waiting for in-flight work"
+    );
+    shutdown();
+    let abandoned = tokio::select! {
+        result = tokio::time::timeout(grace_period + CANCELLATION_MARGIN, &mut run) => match result {
+            Ok(result) => {
+                result?;
+                Vec::new()
+            }
+            Err(_) => in_flight::running(),
+        },
+        signal = shutdown_signal() => {
+            warn!(signal = signal?, "not waiting any longer");
+            in_flight::running()
+        }
+    };
+
+    for activity in &abandoned {
+        warn!(
+            activity_type = %activity.activity_type,
+            workflow_id = %activity.workflow_id,
+            attempt = activity.attempt,
+            running_secs = activity.elapsed().as_secs_f64(),
+            "abandoned activity"
+        );
+    }
+    info!(abandoned = abandoned.len(), "worker stopped");
+    shutdown_tracer(tracer);
     Ok(())
 }
+
+/// Send the spans still buffered for export, if tracing is on.
+fn shutdown_tracer(tracer: Option<SdkTracerProvider>) {
+    if let Some(tracer) = tracer
+        && let Err(error) = tracer.shutdown()
+    {
+        warn!(%error, "couldn't flush trace spans");
+    }
+}
+
+/// Wait for SIGINT (Ctrl-C) or, on Unix, SIGTERM, and name the one that arrived.
+async fn shutdown_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|()| "SIGINT"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|()| "Ctrl-C")
+    }
+}
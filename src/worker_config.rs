@@ -0,0 +1,339 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use temporalio_sdk_core::{FixedSizeSlotSupplier, PollerBehavior, TunerBuilder, TunerHolder};
+
+use crate::approval::ApprovalPolicy;
+use crate::logging::{DEFAULT_LOG_FILTER, LogFormat};
+use crate::overrides::set;
+use crate::shared::MONEY_TRANSFER_TASK_QUEUE_NAME;
+
+pub const DEFAULT_MAX_CONCURRENT_WORKFLOW_TASKS: usize = 100;
+pub const DEFAULT_MAX_CONCURRENT_ACTIVITIES: usize = 100;
+pub const DEFAULT_POLLERS: usize = 5;
+pub const DEFAULT_STICKY_CACHE_SIZE: usize = 1000;
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+// ---------------------------------------------------------------------------
+// Error types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkerConfigError {
+    #[error("can't read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("can't parse worker config {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    #[error("{0} must be at least 1")]
+    Zero(&'static str),
+    #[error("workflow_pollers must be at least 2 while the sticky cache is enabled")]
+    TooFewStickyPollers,
+    #[error("activities_per_second must be a positive number, got {0}")]
+    InvalidRate(f64),
+    #[error("approval_threshold must not be negative, got {0}")]
+    NegativeApprovalThreshold(Decimal),
+}
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// Throughput and identity settings for the worker.
+///
+/// Loaded from a TOML file, then overridden field by field by flags and environment
+/// variables (see [`WorkerArgs`]). Unset fields fall back to this module's defaults, such
+/// as [`DEFAULT_MAX_CONCURRENT_ACTIVITIES`], [`DEFAULT_POLLERS`] and
+/// [`DEFAULT_STICKY_CACHE_SIZE`], or are left off:
+///
+/// ```toml
+/// task_queue = "TRANSFER_MONEY_TASK_QUEUE"
+/// identity = "transfer-worker-1"
+/// max_concurrent_workflow_tasks = 200
+/// max_concurrent_activities = 50
+/// workflow_pollers = 4
+/// activity_pollers = 8
+/// activities_per_second = 20.0
+/// sticky_cache_size = 2000
+/// shutdown_grace_period = 60
+/// metrics_address = "0.0.0.0:9464"
+/// log_format = "json"
+/// log_filter = "info,temporalio_sdk_core=warn"
+/// otlp_endpoint = "http://localhost:4317"
+/// approval_threshold = "2500.00"
+/// approval_timeout = 86400
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerConfig {
+    /// Defaults to [`MONEY_TRANSFER_TASK_QUEUE_NAME`].
+    pub task_queue: Option<String>,
+    /// Overrides the identity the worker reports to the server.
+    pub identity: Option<String>,
+    pub max_concurrent_workflow_tasks: Option<usize>,
+    pub max_concurrent_activities: Option<usize>,
+    pub workflow_pollers: Option<usize>,
+    pub activity_pollers: Option<usize>,
+    /// Most activities this worker starts per second; unlimited when unset.
+    pub activities_per_second: Option<f64>,
+    /// Workflows kept in memory between tasks. Zero disables sticky execution.
+    pub sticky_cache_size: Option<usize>,
+    /// Seconds in-flight activities get to finish after a shutdown signal.
+    pub shutdown_grace_period: Option<u64>,
+    /// Serve Prometheus metrics on this address; off when unset.
+    pub metrics_address: Option<SocketAddr>,
+    pub log_format: Option<LogFormat>,
+    /// Which events to log, in `RUST_LOG` syntax. Defaults to [`DEFAULT_LOG_FILTER`].
+    pub log_filter: Option<String>,
+    /// Export trace spans over OTLP/gRPC to this collector; off when unset.
+    pub otlp_endpoint: Option<String>,
+    /// Transfers of more than this amount wait for an approver.
+    pub approval_threshold: Option<Decimal>,
+    /// Seconds a transfer waits for an approver before it is rejected.
+    pub approval_timeout: Option<u64>,
+}
+
+impl WorkerConfig {
+    /// Read a TOML worker config file.
+    pub fn from_file(path: &Path) -> Result<Self, WorkerConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| WorkerConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|source| WorkerConfigError::Parse {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })
+    }
+
+    /// Check the settings before they reach the SDK, whose errors name its own fields.
+    pub fn validate(&self) -> Result<(), WorkerConfigError> {
+        for (name, value) in [
+            (
+                "max_concurrent_workflow_tasks",
+                self.max_concurrent_workflow_tasks(),
+            ),
+            (
+                "max_concurrent_activities",
+                self.max_concurrent_activities(),
+            ),
+            ("workflow_pollers", self.workflow_pollers()),
+            ("activity_pollers", self.activity_pollers()),
+        ] {
+            if value == 0 {
+                return Err(WorkerConfigError::Zero(name));
+            }
+        }
+        if self.sticky_cache_size() > 0 && self.workflow_pollers() < 2 {
+            return Err(WorkerConfigError::TooFewStickyPollers);
+        }
+        if let Some(rate) = self.activities_per_second
+            && !(rate.is_finite() && rate > 0.0)
+        {
+            return Err(WorkerConfigError::InvalidRate(rate));
+        }
+        if let Some(threshold) = self.approval_threshold
+            && threshold.is_sign_negative()
+        {
+            return Err(WorkerConfigError::NegativeApprovalThreshold(threshold));
+        }
+        if self.approval_timeout == Some(0) {
+            return Err(WorkerConfigError::Zero("approval_timeout"));
+        }
+        Ok(())
+    }
+
+    pub fn task_queue(&self) -> &str {
+        self.task_queue
+            .as_deref()
+            .unwrap_or(MONEY_TRANSFER_TASK_QUEUE_NAME)
+    }
+
+    pub fn max_concurrent_workflow_tasks(&self) -> usize {
+        self.max_concurrent_workflow_tasks
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_WORKFLOW_TASKS)
+    }
+
+    pub fn max_concurrent_activities(&self) -> usize {
+        self.max_concurrent_activities
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_ACTIVITIES)
+    }
+
+    pub fn workflow_pollers(&self) -> usize {
+        self.workflow_pollers.unwrap_or(DEFAULT_POLLERS)
+    }
+
+    pub fn activity_pollers(&self) -> usize {
+        self.activity_pollers.unwrap_or(DEFAULT_POLLERS)
+    }
+
+    pub fn sticky_cache_size(&self) -> usize {
+        self.sticky_cache_size.unwrap_or(DEFAULT_STICKY_CACHE_SIZE)
+    }
+
+    pub fn shutdown_grace_period(&self) -> Duration {
+        self.shutdown_grace_period
+            .map_or(DEFAULT_SHUTDOWN_GRACE_PERIOD, Duration::from_secs)
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format.unwrap_or_default()
+    }
+
+    pub fn log_filter(&self) -> &str {
+        self.log_filter.as_deref().unwrap_or(DEFAULT_LOG_FILTER)
+    }
+
+    pub fn approval_policy(&self) -> ApprovalPolicy {
+        let default = ApprovalPolicy::default();
+        ApprovalPolicy {
+            threshold: self.approval_threshold.unwrap_or(default.threshold),
+            timeout: self
+                .approval_timeout
+                .map_or(default.timeout, Duration::from_secs),
+        }
+    }
+
+    pub fn workflow_poller_behavior(&self) -> PollerBehavior {
+        PollerBehavior::SimpleMaximum(self.workflow_pollers())
+    }
+
+    pub fn activity_poller_behavior(&self) -> PollerBehavior {
+        PollerBehavior::SimpleMaximum(self.activity_pollers())
+    }
+
+    /// A tuner with a fixed number of workflow task and activity slots.
+    pub fn tuner(&self) -> Arc<TunerHolder> {
+        Arc::new(
+            TunerBuilder::default()
+                .workflow_slot_supplier(Arc::new(FixedSizeSlotSupplier::new(
+                    self.max_concurrent_workflow_tasks(),
+                )))
+                .activity_slot_supplier(Arc::new(FixedSizeSlotSupplier::new(
+                    self.max_concurrent_activities(),
+                )))
+                .build(),
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Command-line flags
+// ---------------------------------------------------------------------------
+
+/// Worker flags. Each one overrides the matching field of the `--worker-config` file.
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct WorkerArgs {
+    /// TOML file with worker settings.
+    #[arg(long, env = "TEMPORAL_WORKER_CONFIG")]
+    worker_config: Option<PathBuf>,
+
+    /// Task queue to poll [default: TRANSFER_MONEY_TASK_QUEUE].
+    #[arg(long, env = "TEMPORAL_TASK_QUEUE")]
+    task_queue: Option<String>,
+
+    /// Identity reported to the server [default: rust-worker].
+    #[arg(long, env = "TEMPORAL_WORKER_IDENTITY")]
+    identity: Option<String>,
+
+    /// Most workflow tasks processed at once [default: 100].
+    #[arg(long, env = "TEMPORAL_MAX_CONCURRENT_WORKFLOW_TASKS")]
+    max_concurrent_workflow_tasks: Option<usize>,
+
+    /// Most activities run at once [default: 100].
+    #[arg(long, env = "TEMPORAL_MAX_CONCURRENT_ACTIVITIES")]
+    max_concurrent_activities: Option<usize>,
+
+    /// Most concurrent workflow task polls [default: 5].
+    #[arg(long, env = "TEMPORAL_WORKFLOW_POLLERS")]
+    workflow_pollers: Option<usize>,
+
+    /// Most concurrent activity task polls [default: 5].
+    #[arg(long, env = "TEMPORAL_ACTIVITY_POLLERS")]
+    activity_pollers: Option<usize>,
+
+    /// Most activities this worker starts per second [default: unlimited].
+    #[arg(long, env = "TEMPORAL_ACTIVITIES_PER_SECOND")]
+    activities_per_second: Option<f64>,
+
+    /// Workflows cached between tasks; 0 disables sticky execution [default: 1000].
+    #[arg(long, env = "TEMPORAL_STICKY_CACHE_SIZE")]
+    sticky_cache_size: Option<usize>,
+
+    /// Seconds in-flight activities get to finish after SIGINT or SIGTERM [default: 30].
+    #[arg(long, env = "TEMPORAL_SHUTDOWN_GRACE_PERIOD")]
+    shutdown_grace_period: Option<u64>,
+
+    /// Serve Prometheus metrics at http://<address>/metrics [default: off].
+    #[arg(long, env = "TEMPORAL_METRICS_ADDRESS")]
+    metrics_address: Option<SocketAddr>,
+
+    /// Log output format [default: text].
+    #[arg(long, value_enum, env = "TEMPORAL_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+
+    /// Which events to log, e.g. `info,temporalio_sdk_core=warn` [default: info].
+    #[arg(long, env = "RUST_LOG")]
+    log_filter: Option<String>,
+
+    /// Export trace spans over OTLP/gRPC to this collector [default: off].
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
+    /// Transfers of more than this amount wait for an approver [default: 1000].
+    #[arg(long, env = "TEMPORAL_APPROVAL_THRESHOLD")]
+    approval_threshold: Option<Decimal>,
+
+    /// Seconds a transfer waits for an approver before it is rejected [default: 259200].
+    #[arg(long, env = "TEMPORAL_APPROVAL_TIMEOUT")]
+    approval_timeout: Option<u64>,
+}
+
+impl WorkerArgs {
+    /// The config file, if any, with every flag or environment variable that was set
+    /// applied on top, validated.
+    pub fn config(&self) -> Result<WorkerConfig, WorkerConfigError> {
+        let mut config = match &self.worker_config {
+            Some(path) => WorkerConfig::from_file(path)?,
+            None => WorkerConfig::default(),
+        };
+        set(&mut config.task_queue, &self.task_queue);
+        set(&mut config.identity, &self.identity);
+        set(
+            &mut config.max_concurrent_workflow_tasks,
+            &self.max_concurrent_workflow_tasks,
+        );
+        set(
+            &mut config.max_concurrent_activities,
+            &self.max_concurrent_activities,
+        );
+        set(&mut config.workflow_pollers, &self.workflow_pollers);
+        set(&mut config.activity_pollers, &self.activity_pollers);
+        set(
+            &mut config.activities_per_second,
+            &self.activities_per_second,
+        );
+        set(&mut config.sticky_cache_size, &self.sticky_cache_size);
+        set(
+            &mut config.shutdown_grace_period,
+            &self.shutdown_grace_period,
+        );
+        set(&mut config.metrics_address, &self.metrics_address);
+        set(&mut config.log_format, &self.log_format);
+        set(&mut config.log_filter, &self.log_filter);
+        set(&mut config.otlp_endpoint, &self.otlp_endpoint);
+        set(&mut config.approval_threshold, &self.approval_threshold);
+        set(&mut config.approval_timeout, &self.approval_timeout);
+        config.validate()?;
+        Ok(config)
+    }
+}
@@ -1,13 +1,53 @@
+use std::pin::pin;
+
+use futures::{
+    FutureExt,
+    future::{Either, select},
+};
 use prost_wkt_types::Duration;
-use temporalio_common::protos::temporal::api::common::v1::RetryPolicy;
+use rust_decimal::Decimal;
+use temporalio_common::protos::temporal::api::{
+    common::v1::RetryPolicy, failure::v1::failure::FailureInfo,
+};
 use temporalio_macros::{workflow, workflow_methods};
-use temporalio_sdk::{ActivityOptions, WorkflowContext, WorkflowResult};
+use temporalio_sdk::{
+    ActivityExecutionError, ActivityOptions, CancellableFuture, LocalActivityOptions,
+    SyncWorkflowContext, WorkflowContext, WorkflowContextView, WorkflowResult, WorkflowTermination,
+};
+use tracing::{Instrument, Span, info_span};
+
+use crate::{
+    activity::Activities,
+    approval::DEFAULT_APPROVAL_THRESHOLD,
+    metrics,
+    search_attributes::{TransferStatus, status_search_attribute, transfer_memo},
+    shared::{PaymentDetails, RoutedPaymentDetails, TransferResult},
+    trace_context::{TraceContext, Traced},
+};
 
-use crate::{activity::Activities, shared::PaymentDetails};
+/// Marks runs that read an [`ApprovalPolicy`](crate::approval::ApprovalPolicy) and give up
+/// waiting for approval when its timeout passes.
+const APPROVAL_POLICY_PATCH: &str = "approval-policy";
 
+/// Moves funds from one account to another. Transfers above the worker's
+/// [`ApprovalPolicy`](crate::approval::ApprovalPolicy) threshold wait for an `approve` or
+/// `reject` signal, or an `approve_pending` or `reject_pending` update, before anything is
+/// withdrawn, and are
+/// rejected if none arrives within the policy's timeout; `status` can be queried at any
+/// point.
 #[workflow]
 #[derive(Default)]
-pub struct MoneyTransferWorkflow;
+pub struct MoneyTransferWorkflow {
+    status: TransferStatus,
+    /// The first decision received; later signals are ignored and updates rejected.
+    decision: Option<ApprovalDecision>,
+}
+
+#[derive(Clone)]
+enum ApprovalDecision {
+    Approved { approver: String },
+    Rejected { reason: String },
+}
 
 #[workflow_methods]
 impl MoneyTransferWorkflow {
@@ -15,48 +55,555 @@ impl MoneyTransferWorkflow {
     pub async fn run(
         ctx: &mut WorkflowContext<Self>,
         input: PaymentDetails,
+    ) -> WorkflowResult<TransferResult> {
+        ctx.upsert_memo([transfer_memo(&input)]);
+        let trace = starter_trace(ctx);
+
+        let result = transfer(ctx, &input, &trace)
+            .instrument(workflow_span(ctx))
+            .await;
+
+        let status = match &result {
+            Ok(_) => TransferStatus::Completed,
+            Err(WorkflowTermination::Cancelled) => TransferStatus::Cancelled,
+            Err(WorkflowTermination::Failed(e)) => match e.downcast_ref::<TransferError>() {
+                Some(TransferError::Compensated(_)) => TransferStatus::Compensated,
+                Some(TransferError::Rejected(_)) => TransferStatus::Rejected,
+                _ => TransferStatus::Failed,
+            },
+            Err(_) => TransferStatus::Failed,
+        };
+        set_status(ctx, status);
+        // Not counted on replay, but counted again if this task has to be retried.
+        if !ctx.is_replaying() {
+            metrics::record_transfer(status, input.amount);
+        }
+
+        result
+    }
+
+    /// Approve a transfer that is waiting for approval, recording who approved it.
+    #[signal]
+    pub fn approve(&mut self, _ctx: &mut SyncWorkflowContext<Self>, approver: String) {
+        self.decision
+            .get_or_insert(ApprovalDecision::Approved { approver });
+    }
+
+    /// Turn down a transfer that is waiting for approval.
+    #[signal]
+    pub fn reject(&mut self, _ctx: &mut SyncWorkflowContext<Self>, reason: String) {
+        self.decision
+            .get_or_insert(ApprovalDecision::Rejected { reason });
+    }
+
+    /// Approve a transfer like the `approve` signal does, but answer the caller: the
+    /// update is rejected unless the transfer is waiting for a decision, and returns the
+    /// approver once it is recorded.
+    #[update]
+    pub fn approve_pending(
+        &mut self,
+        _ctx: &mut SyncWorkflowContext<Self>,
+        approver: String,
+    ) -> String {
+        self.decision = Some(ApprovalDecision::Approved {
+            approver: approver.clone(),
+        });
+        approver
+    }
+
+    #[update_validator(approve_pending)]
+    pub fn validate_approve_pending(
+        &self,
+        _ctx: &WorkflowContextView,
+        _approver: &String,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.awaiting_decision()
+    }
+
+    /// Reject a transfer like the `reject` signal does, but answer the caller: the update
+    /// is rejected unless the transfer is waiting for a decision, and returns the reason
+    /// once it is recorded.
+    #[update]
+    pub fn reject_pending(
+        &mut self,
+        _ctx: &mut SyncWorkflowContext<Self>,
+        reason: String,
+    ) -> String {
+        self.decision = Some(ApprovalDecision::Rejected {
+            reason: reason.clone(),
+        });
+        reason
+    }
+
+    #[update_validator(reject_pending)]
+    pub fn validate_reject_pending(
+        &self,
+        _ctx: &WorkflowContextView,
+        _reason: &String,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.awaiting_decision()
+    }
+
+    /// Where the transfer is up to.
+    #[query]
+    pub fn status(&self, _ctx: &WorkflowContextView) -> TransferStatus {
+        self.status
+    }
+}
+
+impl MoneyTransferWorkflow {
+    /// Refuse a decision unless the transfer is waiting for one.
+    fn awaiting_decision(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match (self.status, &self.decision) {
+            (TransferStatus::AwaitingApproval, None) => Ok(()),
+            (_, Some(_)) => Err("the transfer has already been decided".into()),
+            (status, None) => {
+                Err(format!("the transfer is {status:?}, not awaiting approval").into())
+            }
+        }
+    }
+}
+
+async fn transfer(
+    ctx: &WorkflowContext<MoneyTransferWorkflow>,
+    input: &PaymentDetails,
+    trace: &TraceContext,
+) -> WorkflowResult<TransferResult> {
+    // Only the activity's own verdict makes the payment invalid. Anything else, such as a
+    // timeout, fails the transfer as it is.
+    match ctx
+        .start_local_activity(
+            Activities::validate_payment,
+            input.clone(),
+            local_activity_options(),
+        )
+        .await
+    {
+        Err(e) if is_non_retryable(&e) => {
+            return Err(WorkflowTermination::failed(TransferError::InvalidPayment(
+                e.to_string(),
+            )));
+        }
+        result => result?,
+    }
+    // Runs started before the approval policy existed have no record of it in their
+    // history, and keep the fixed threshold and open-ended wait they started with.
+    let approval = if ctx.patched(APPROVAL_POLICY_PATCH) {
+        Some(
+            ctx.start_local_activity(Activities::approval_policy, (), local_activity_options())
+                .await?,
+        )
+    } else {
+        None
+    };
+
+    // Nothing has moved yet, so a cancellation up to here just abandons the transfer.
+    let fee = unless_cancelled(
+        ctx,
+        ctx.start_activity(
+            Activities::calculate_fee,
+            Traced::new(input.clone(), trace),
+            activity_options(),
+        ),
+    )
+    .await
+    .ok_or(WorkflowTermination::Cancelled)??;
+    let requires_approval = approval.map_or(input.amount > DEFAULT_APPROVAL_THRESHOLD, |p| {
+        p.requires_approval(input.amount)
+    });
+    let approved_by = if requires_approval {
+        Some(await_approval(ctx, approval.map(|p| p.timeout)).await?)
+    } else {
+        None
+    };
+    if cancellation_requested(ctx).is_some() {
+        return Err(WorkflowTermination::Cancelled);
+    }
+
+    // The fee leaves the source account in the same withdrawal as the principal.
+    let debit = Traced::new(
+        PaymentDetails {
+            amount: input.amount + fee.fee,
+            ..input.clone()
+        },
+        trace,
+    );
+    // Once a withdrawal is sent there's no telling whether the bank applied it, so it
+    // always runs to completion.
+    let withdraw_output = ctx
+        .start_activity(Activities::withdraw, debit.clone(), activity_options())
+        .await?;
+    set_status(ctx, TransferStatus::Withdrawn);
+
+    if cancellation_requested(ctx).is_some() {
+        // Activities aren't cancelled along with the workflow, so the refund can't be
+        // interrupted by the cancellation that triggered it.
+        refund(ctx, debit).await?;
+        return Err(WorkflowTermination::Cancelled);
+    }
+
+    let deposit_output = match ctx
+        .start_activity(
+            Activities::deposit,
+            Traced::new(input.clone(), trace),
+            activity_options(),
+        )
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            // Return the whole debit, fee included, before failing the transfer.
+            refund(ctx, debit).await?;
+            return Err(WorkflowTermination::failed(TransferError::Compensated(
+                e.to_string(),
+            )));
+        }
+    };
+    set_status(ctx, TransferStatus::Deposited);
+
+    let fee_output = if fee.fee > Decimal::ZERO {
+        let posting = Traced::new(
+            PaymentDetails {
+                amount: fee.fee,
+                target_account: fee.fee_account.clone(),
+                ..input.clone()
+            },
+            trace,
+        );
+        Some(
+            ctx.start_activity(Activities::post_fee, posting, activity_options())
+                .await?,
+        )
+    } else {
+        None
+    };
+
+    let result = TransferResult {
+        withdraw_transaction_id: withdraw_output,
+        deposit_transaction_id: deposit_output,
+        fee_transaction_id: fee_output,
+        principal: input.amount,
+        total_debited: input.amount + fee.fee,
+        fee,
+        approved_by,
+        late_cancellation: cancellation_requested(ctx),
+    };
+
+    Ok(result)
+}
+
+/// Wait up to `timeout` for an `approve` or `reject` signal, returning the approver.
+/// A transfer nobody decides on in time is rejected; without a timeout it waits for as
+/// long as it takes.
+async fn await_approval(
+    ctx: &WorkflowContext<MoneyTransferWorkflow>,
+    timeout: Option<std::time::Duration>,
+) -> WorkflowResult<String> {
+    set_status(ctx, TransferStatus::AwaitingApproval);
+    let decided = pin!(ctx.wait_condition(|s| s.decision.is_some()));
+    let cancelled = pin!(ctx.cancelled());
+    let Some(timeout) = timeout else {
+        if let Either::Right(_) = select(decided, cancelled).await {
+            return Err(WorkflowTermination::Cancelled);
+        }
+        return approval_decision(ctx);
+    };
+    let deadline = pin!(ctx.timer(timeout));
+    match select(select(decided, cancelled), deadline).await {
+        Either::Left((Either::Left(_), deadline)) => {
+            deadline.cancel();
+            deadline.await;
+        }
+        Either::Left((Either::Right(_), deadline)) => {
+            deadline.cancel();
+            deadline.await;
+            return Err(WorkflowTermination::Cancelled);
+        }
+        Either::Right(_) => {
+            return Err(WorkflowTermination::failed(TransferError::Rejected(
+                format!(
+                    "no decision within {} hours",
+                    timeout.as_secs_f64() / 3600.0
+                ),
+            )));
+        }
+    }
+    approval_decision(ctx)
+}
+
+/// The approver of a decided transfer, or the rejection if it was turned down.
+fn approval_decision(ctx: &WorkflowContext<MoneyTransferWorkflow>) -> WorkflowResult<String> {
+    match ctx.state(|s| s.decision.clone()) {
+        Some(ApprovalDecision::Approved { approver }) => Ok(approver),
+        Some(ApprovalDecision::Rejected { reason }) => {
+            Err(WorkflowTermination::failed(TransferError::Rejected(reason)))
+        }
+        None => unreachable!("wait_condition returned before a decision"),
+    }
+}
+
+/// The trace context the starter sent, which activities continue from.
+fn starter_trace<W>(ctx: &WorkflowContext<W>) -> TraceContext {
+    TraceContext::from_headers(&ctx.workflow_initial_info().headers)
+}
+
+/// A span for this run of the workflow, a child of the starter's span.
+///
+/// A worker replaying the run, e.g. after evicting it from its cache, can't pick up the
+/// span it started before, so it doesn't start one at all. For the same reason activities
+/// continue the starter's span rather than this one: that context is the same on every
+/// replay.
+fn workflow_span<W>(ctx: &WorkflowContext<W>) -> Span {
+    if ctx.is_replaying() {
+        return Span::none();
+    }
+    let info = ctx.workflow_initial_info();
+    let span = info_span!(
+        "workflow",
+        otel.name = %format!("RunWorkflow:{}", info.workflow_type),
+        workflow_id = %info.workflow_id,
+    );
+    starter_trace(ctx).set_as_parent_of(&span);
+    span
+}
+
+/// Record a new status for both the `status` query and the `TransferStatus` search attribute.
+fn set_status(ctx: &WorkflowContext<MoneyTransferWorkflow>, status: TransferStatus) {
+    ctx.state_mut(|s| s.status = status);
+    ctx.upsert_search_attributes([status_search_attribute(status)]);
+}
+
+/// Put a withdrawal back into the source account.
+async fn refund(
+    ctx: &WorkflowContext<MoneyTransferWorkflow>,
+    debit: Traced<PaymentDetails>,
+) -> WorkflowResult<()> {
+    ctx.start_activity(Activities::refund, debit, activity_options())
+        .await
+        .map_err(|e| WorkflowTermination::failed(TransferError::RefundFailed(e.to_string())))?;
+    Ok(())
+}
+
+/// Whether the activity failed with an error it marked non-retryable, rather than timing
+/// out, being cancelled or running out of attempts.
+pub fn is_non_retryable(error: &ActivityExecutionError) -> bool {
+    let ActivityExecutionError::Failed(failure) = error else {
+        return false;
+    };
+    std::iter::successors(Some(failure.as_ref()), |f| f.cause.as_deref()).any(|f| {
+        matches!(
+            &f.failure_info,
+            Some(FailureInfo::ApplicationFailureInfo(info)) if info.non_retryable
+        )
+    })
+}
+
+/// The reason given for cancelling the workflow, if a cancellation has been requested.
+fn cancellation_requested(ctx: &WorkflowContext<MoneyTransferWorkflow>) -> Option<String> {
+    ctx.cancelled().now_or_never()
+}
+
+/// Wait for `activity`, or cancel it and return `None` if the workflow is cancelled first.
+async fn unless_cancelled<T>(
+    ctx: &WorkflowContext<MoneyTransferWorkflow>,
+    activity: impl CancellableFuture<T>,
+) -> Option<T> {
+    let activity = pin!(activity);
+    let cancelled = pin!(ctx.cancelled());
+    match select(activity, cancelled).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right((_reason, activity)) => {
+            activity.cancel();
+            activity.await;
+            None
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    #[error("invalid payment details: {0}")]
+    InvalidPayment(String),
+    #[error("deposit failed and the withdrawal was refunded: {0}")]
+    Compensated(String),
+    #[error("refund failed, funds are held by the bank: {0}")]
+    RefundFailed(String),
+    #[error("transfer rejected: {0}")]
+    Rejected(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoutedTransferError {
+    #[error("a route needs at least a source and a target account, got {0} account(s)")]
+    RouteTooShort(usize),
+    #[error(
+        "leg {leg} ({source_account} -> {target_account}) failed: {message}; unwound {unwound} completed leg(s)"
+    )]
+    LegFailed {
+        leg: usize,
+        source_account: String,
+        target_account: String,
+        message: String,
+        unwound: usize,
+    },
+    #[error("compensation of leg {leg} failed: {message}")]
+    CompensationFailed { leg: usize, message: String },
+}
+
+/// Moves funds along a route of several hops. Each hop runs as its own withdraw/deposit
+/// pair; if a later hop fails, the completed hops are unwound in reverse order.
+#[workflow]
+#[derive(Default)]
+pub struct RoutedMoneyTransferWorkflow;
+
+#[workflow_methods]
+impl RoutedMoneyTransferWorkflow {
+    #[run]
+    pub async fn run(
+        ctx: &mut WorkflowContext<Self>,
+        input: RoutedPaymentDetails,
     ) -> WorkflowResult<String> {
-        let retry_policy = RetryPolicy {
-            initial_interval: Some(Duration {
-                seconds: 1,
-                nanos: 0,
-            }),
-            backoff_coefficient: 2.0,
-            maximum_interval: Some(Duration {
-                seconds: 5,
-                nanos: 0,
-            }),
-            maximum_attempts: 5,
-            non_retryable_error_types: vec![
-                "InvalidAccountError".to_string(),
-                "InsufficentFundsError".to_string(),
-            ],
+        let trace = starter_trace(ctx);
+        route(ctx, input, &trace)
+            .instrument(workflow_span(ctx))
+            .await
+    }
+}
+
+async fn route(
+    ctx: &WorkflowContext<RoutedMoneyTransferWorkflow>,
+    input: RoutedPaymentDetails,
+    trace: &TraceContext,
+) -> WorkflowResult<String> {
+    let legs: Vec<Traced<PaymentDetails>> = input
+        .legs()
+        .into_iter()
+        .map(|leg| Traced::new(leg, trace))
+        .collect();
+    if legs.is_empty() {
+        return Err(WorkflowTermination::failed(
+            RoutedTransferError::RouteTooShort(input.route.len()),
+        ));
+    }
+
+    let mut completed: Vec<Traced<PaymentDetails>> = Vec::new();
+    let mut transaction_ids = Vec::new();
+
+    for (i, leg) in legs.iter().enumerate() {
+        let withdraw_output = match ctx
+            .start_activity(Activities::withdraw, leg.clone(), activity_options())
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                unwind_legs(ctx, &completed).await?;
+                return Err(leg_failed(i + 1, &leg.value, e, completed.len()));
+            }
         };
-        let withdraw_options = ActivityOptions {
-            start_to_close_timeout: Some(std::time::Duration::from_secs_f64(60.)),
-            retry_policy: Some(retry_policy.clone()),
-            ..Default::default()
+
+        let deposit_output = match ctx
+            .start_activity(Activities::deposit, leg.clone(), activity_options())
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                // The funds already left this leg's source account, so put them back
+                // before unwinding the legs that completed before it.
+                ctx.start_activity(Activities::refund, leg.clone(), activity_options())
+                    .await
+                    .map_err(|e| compensation_failed(i + 1, e))?;
+                unwind_legs(ctx, &completed).await?;
+                return Err(leg_failed(i + 1, &leg.value, e, completed.len()));
+            }
         };
 
-        let withdraw_output = ctx
-            .start_activity(Activities::withdraw, input.clone(), withdraw_options)
-            .await?;
+        transaction_ids.push(withdraw_output);
+        transaction_ids.push(deposit_output);
+        completed.push(leg.clone());
+    }
 
-        let deposit_options = ActivityOptions {
-            start_to_close_timeout: Some(std::time::Duration::from_secs_f64(60.)),
-            retry_policy: Some(retry_policy),
-            ..Default::default()
-        };
+    let result = format!(
+        "Routed transfer complete over {} legs (transaction IDs: {})",
+        legs.len(),
+        transaction_ids.join(", ")
+    );
 
-        let deposit_output = ctx
-            .start_activity(Activities::deposit, input, deposit_options)
-            .await?;
+    Ok(result)
+}
 
-        let result = format!(
-            "Transfer complete (transaction IDs: {}, {})",
-            withdraw_output, deposit_output
+/// Reverse completed legs, last one first: withdraw the funds from each leg's target
+/// and refund them into its source.
+async fn unwind_legs(
+    ctx: &WorkflowContext<RoutedMoneyTransferWorkflow>,
+    completed: &[Traced<PaymentDetails>],
+) -> WorkflowResult<()> {
+    for (i, leg) in completed.iter().enumerate().rev() {
+        let reversal = Traced::new(
+            PaymentDetails {
+                amount: leg.value.amount,
+                source_account: leg.value.target_account.clone(),
+                target_account: leg.value.source_account.clone(),
+                reference_id: format!("{}-reversal", leg.value.reference_id),
+            },
+            &leg.trace_context,
         );
+        ctx.start_activity(Activities::withdraw, reversal, activity_options())
+            .await
+            .map_err(|e| compensation_failed(i + 1, e))?;
+        ctx.start_activity(Activities::refund, leg.clone(), activity_options())
+            .await
+            .map_err(|e| compensation_failed(i + 1, e))?;
+    }
+    Ok(())
+}
+
+fn leg_failed(
+    leg: usize,
+    details: &PaymentDetails,
+    error: ActivityExecutionError,
+    unwound: usize,
+) -> WorkflowTermination {
+    WorkflowTermination::failed(RoutedTransferError::LegFailed {
+        leg,
+        source_account: details.source_account.clone(),
+        target_account: details.target_account.clone(),
+        message: error.to_string(),
+        unwound,
+    })
+}
+
+fn compensation_failed(leg: usize, error: ActivityExecutionError) -> WorkflowTermination {
+    WorkflowTermination::failed(RoutedTransferError::CompensationFailed {
+        leg,
+        message: error.to_string(),
+    })
+}
+
+fn local_activity_options() -> LocalActivityOptions {
+    LocalActivityOptions {
+        start_to_close_timeout: Some(std::time::Duration::from_secs(5)),
+        ..Default::default()
+    }
+}
 
-        Ok(result)
+fn activity_options() -> ActivityOptions {
+    let retry_policy = RetryPolicy {
+        initial_interval: Some(Duration {
+            seconds: 1,
+            nanos: 0,
+        }),
+        backoff_coefficient: 2.0,
+        maximum_interval: Some(Duration {
+            seconds: 5,
+            nanos: 0,
+        }),
+        maximum_attempts: 5,
+        // Bank errors that retrying can't fix are marked non-retryable by the activities.
+        ..Default::default()
+    };
+    ActivityOptions {
+        start_to_close_timeout: Some(std::time::Duration::from_secs_f64(60.)),
+        retry_policy: Some(retry_policy),
+        ..Default::default()
     }
 }
@@ -0,0 +1,53 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use temporalio_common::protos::temporal::api::command::v1::ScheduleActivityTaskCommandAttributes;
+use temporalio_sdk::ActivityOptions;
+
+/// An activity the workflow scheduled, as recorded by the harness.
+///
+/// Local activities run on the worker itself and aren't recorded.
+#[derive(Debug)]
+pub struct ActivityCall {
+    /// The activity type, e.g. `Activities::withdraw`.
+    pub activity_type: String,
+    /// The options the workflow scheduled the activity with. Only the fields that reach
+    /// the server are filled in: the id, task queue, timeouts and retry policy.
+    pub options: ActivityOptions,
+    /// The attempt that settled the call, counting from 1; earlier attempts failed and
+    /// were retried.
+    pub attempt: u32,
+    input: Value,
+}
+
+impl ActivityCall {
+    pub(crate) fn new(attrs: &ScheduleActivityTaskCommandAttributes, input: Value) -> Self {
+        Self {
+            activity_type: attrs
+                .activity_type
+                .as_ref()
+                .map(|t| t.name.clone())
+                .unwrap_or_default(),
+            options: ActivityOptions {
+                activity_id: Some(attrs.activity_id.clone()),
+                task_queue: attrs.task_queue.as_ref().map(|q| q.name.clone()),
+                schedule_to_start_timeout: duration(attrs.schedule_to_start_timeout),
+                start_to_close_timeout: duration(attrs.start_to_close_timeout),
+                schedule_to_close_timeout: duration(attrs.schedule_to_close_timeout),
+                heartbeat_timeout: duration(attrs.heartbeat_timeout),
+                retry_policy: attrs.retry_policy.clone(),
+                ..Default::default()
+            },
+            attempt: 1,
+            input,
+        }
+    }
+
+    /// Deserialize the input the workflow passed to the activity.
+    pub fn input<T: DeserializeOwned>(&self) -> Option<T> {
+        T::deserialize(&self.input).ok()
+    }
+}
+
+fn duration<D: TryInto<std::time::Duration>>(d: Option<D>) -> Option<std::time::Duration> {
+    d.and_then(|d| d.try_into().ok())
+}
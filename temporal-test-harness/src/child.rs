@@ -0,0 +1,171 @@
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime};
+
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+use serde_json::Value;
+use temporalio_common::protos::{
+    coresdk::AsJsonPayloadExt,
+    temporal::api::{
+        common::v1::{Payloads, WorkflowExecution, WorkflowType},
+        failure::v1::Failure,
+    },
+};
+use temporalio_sdk::workflows::WorkflowImplementer;
+
+use crate::error::{TestHarnessError, WorkflowTestResult};
+use crate::runner::TestWorkflowEnvironment;
+
+/// How a child workflow finished, as its parent sees it.
+#[derive(Clone)]
+pub(crate) enum ChildOutcome {
+    Completed(Option<Payloads>),
+    Failed(Box<Failure>),
+}
+
+impl From<WorkflowTestResult> for ChildOutcome {
+    fn from(result: WorkflowTestResult) -> Self {
+        match result {
+            Ok(payloads) => Self::Completed(payloads),
+            Err(failure) => Self::Failed(Box::new(failure.failure.unwrap_or(Failure {
+                message: failure.message,
+                ..Default::default()
+            }))),
+        }
+    }
+}
+
+type ChildRunner = Box<
+    dyn FnOnce(Value, SystemTime) -> LocalBoxFuture<'static, Result<ChildRun, TestHarnessError>>
+        + Send,
+>;
+
+/// A child workflow run in its own test environment: how it finished and how long it took
+/// on the virtual clock.
+pub(crate) struct ChildRun {
+    outcome: ChildOutcome,
+    took: Duration,
+}
+
+/// How a mocked child workflow answers.
+enum ChildBehaviour {
+    /// Finish straight away with this outcome.
+    Fixed(ChildOutcome),
+    /// Run the real workflow in a nested environment.
+    Execute(ChildRunner),
+}
+
+/// A registered child workflow mock, used up by the first child of its type.
+pub(crate) struct MockedChildWorkflow {
+    pub(crate) workflow_type: String,
+    behaviour: ChildBehaviour,
+}
+
+impl MockedChildWorkflow {
+    /// Run the child with `input`, starting at `now`, and return how it finished and how
+    /// long after `now` it did.
+    pub(crate) async fn run(
+        self,
+        input: Value,
+        now: SystemTime,
+    ) -> Result<(ChildOutcome, Duration), TestHarnessError> {
+        match self.behaviour {
+            ChildBehaviour::Fixed(outcome) => Ok((outcome, Duration::ZERO)),
+            ChildBehaviour::Execute(run) => {
+                let run = run(input, now)
+                    .await
+                    .map_err(|e| TestHarnessError::ChildWorkflow {
+                        workflow_type: self.workflow_type,
+                        source: Box::new(e),
+                    })?;
+                Ok((run.outcome, run.took))
+            }
+        }
+    }
+}
+
+/// A child workflow the workflow under test started that hasn't finished yet.
+pub(crate) struct StartedChild {
+    pub(crate) initiated_event_id: i64,
+    pub(crate) started_event_id: i64,
+    pub(crate) execution: WorkflowExecution,
+    pub(crate) workflow_type: Option<WorkflowType>,
+    pub(crate) outcome: ChildOutcome,
+    /// Virtual time after the parent's start at which the child finishes.
+    pub(crate) finishes_at: Duration,
+}
+
+/// Fluent handle for mocking a child workflow of type `W`.
+///
+/// Created by [`TestWorkflowEnvironment::on_child_workflow`]. Each mock answers one
+/// child, in the order they were registered; the borrow is released when `.returns()`,
+/// `.returns_err()` or `.executes()` is called.
+pub struct ChildWorkflowMockCall<'a, W> {
+    pub(crate) child_mocks: &'a mut Vec<MockedChildWorkflow>,
+    pub(crate) workflow: PhantomData<W>,
+}
+
+impl<W> ChildWorkflowMockCall<'_, W>
+where
+    W: WorkflowImplementer + 'static,
+    <W::Run as temporalio_common::WorkflowDefinition>::Input: Send,
+{
+    /// Mock the child to complete straight away with the given JSON-serializable value.
+    pub fn returns<T: Serialize>(self, value: T) {
+        let payload = value
+            .as_json_payload()
+            .expect("child workflow result must be JSON-serializable");
+        self.push(ChildBehaviour::Fixed(ChildOutcome::Completed(Some(
+            Payloads {
+                payloads: vec![payload],
+            },
+        ))));
+    }
+
+    /// Mock the child to fail straight away with the given error message.
+    pub fn returns_err(self, message: &str) {
+        self.push(ChildBehaviour::Fixed(ChildOutcome::Failed(Box::new(
+            Failure {
+                message: message.to_string(),
+                ..Default::default()
+            },
+        ))));
+    }
+
+    /// Run the real child workflow in a fresh environment that `configure` sets up with
+    /// its own activities and mocks. The child starts at the parent's virtual time, and
+    /// its result reaches the parent once the child's run time has passed on the parent's
+    /// clock.
+    ///
+    /// ```ignore
+    /// env.on_child_workflow::<MoneyTransferWorkflow>().executes(|child| {
+    ///     child.register_activities(Activities);
+    ///     child.on_activity("Activities::withdraw").returns("W1234");
+    ///     child.on_activity("Activities::deposit").returns("D5678");
+    /// });
+    /// ```
+    pub fn executes(self, configure: impl FnOnce(&mut TestWorkflowEnvironment) + Send + 'static) {
+        self.push(ChildBehaviour::Execute(Box::new(
+            move |input, start_time| {
+                Box::pin(async move {
+                    let mut env = TestWorkflowEnvironment::new();
+                    env.set_start_time(start_time);
+                    configure(&mut env);
+                    env.execute_workflow::<W>(input).await?;
+                    let outcome = env.take_result().ok_or(TestHarnessError::NoResult)?;
+                    Ok(ChildRun {
+                        outcome: outcome.into(),
+                        took: env.now().duration_since(start_time).unwrap_or_default(),
+                    })
+                })
+            },
+        )));
+    }
+
+    fn push(self, behaviour: ChildBehaviour) {
+        self.child_mocks.push(MockedChildWorkflow {
+            workflow_type: W::name().to_string(),
+            behaviour,
+        });
+    }
+}
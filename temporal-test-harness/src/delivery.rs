@@ -0,0 +1,130 @@
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use temporalio_common::protos::{coresdk::AsJsonPayloadExt, temporal::api::common::v1::Payload};
+
+use crate::history::SyntheticHistory;
+use crate::update::UpdateHandle;
+
+type Callback = Box<dyn FnOnce(&mut CallbackContext) + Send>;
+
+/// Something the harness delivers to the workflow partway through the run.
+pub(crate) enum Delivery {
+    Cancel {
+        reason: String,
+    },
+    Signal {
+        name: String,
+        input: Payload,
+    },
+    Update {
+        handle: UpdateHandle,
+        name: String,
+        input: Payload,
+    },
+    /// Test code deciding what to deliver when the time comes.
+    Callback(Callback),
+}
+
+impl Delivery {
+    pub(crate) fn signal(name: &str, input: impl Serialize) -> Self {
+        let input = input
+            .as_json_payload()
+            .expect("signal input must be JSON-serializable");
+        Self::Signal {
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    pub(crate) fn update(handle: UpdateHandle, name: &str, input: impl Serialize) -> Self {
+        let input = input
+            .as_json_payload()
+            .expect("update input must be JSON-serializable");
+        Self::Update {
+            handle,
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    /// Add the delivery to `history`, running a callback first to find out what it
+    /// delivers.
+    pub(crate) fn deliver(self, history: &mut SyntheticHistory) {
+        match self {
+            Self::Cancel { reason } => history.cancel_requested(&reason),
+            Self::Signal { name, input } => history.signaled(&name, input),
+            Self::Update {
+                handle,
+                name,
+                input,
+            } => history.request_update(handle.id(), &name, input),
+            Self::Callback(callback) => {
+                let mut ctx = CallbackContext {
+                    now: history.now(),
+                    deliveries: Vec::new(),
+                };
+                callback(&mut ctx);
+                for delivery in ctx.deliveries {
+                    delivery.deliver(history);
+                }
+            }
+        }
+    }
+}
+
+/// When a [`Delivery`] is made.
+pub(crate) enum Trigger {
+    /// As soon as the first call to this activity resolves, in the same workflow task
+    /// that delivers its result.
+    AfterActivity(String),
+    /// Once the virtual clock is this far past the workflow's start.
+    At(Duration),
+}
+
+pub(crate) struct ScheduledDelivery {
+    pub(crate) trigger: Trigger,
+    pub(crate) delivery: Delivery,
+}
+
+/// What a delayed callback registered with
+/// [`TestWorkflowEnvironment::register_delayed_callback`] can do to the workflow.
+///
+/// Signals, updates and cancellation requests are delivered in the workflow task that follows
+/// the callback, in the order they were made.
+///
+/// [`TestWorkflowEnvironment::register_delayed_callback`]: crate::TestWorkflowEnvironment::register_delayed_callback
+pub struct CallbackContext {
+    now: SystemTime,
+    deliveries: Vec<Delivery>,
+}
+
+impl CallbackContext {
+    /// The virtual time the callback runs at.
+    pub fn now(&self) -> SystemTime {
+        self.now
+    }
+
+    /// Send the signal `name` to the workflow.
+    pub fn signal_workflow(&mut self, name: &str, input: impl Serialize) {
+        self.deliveries.push(Delivery::signal(name, input));
+    }
+
+    /// Send the update `name` to the workflow. Its outcome can be looked up with
+    /// [`TestWorkflowEnvironment::update_outcome`] once the workflow has run.
+    ///
+    /// [`TestWorkflowEnvironment::update_outcome`]: crate::TestWorkflowEnvironment::update_outcome
+    pub fn update_workflow(&mut self, name: &str, input: impl Serialize) -> UpdateHandle {
+        let handle = UpdateHandle::new();
+        self.deliveries
+            .push(Delivery::update(handle.clone(), name, input));
+        handle
+    }
+
+    /// Ask for the workflow to be cancelled.
+    pub fn cancel_workflow(&mut self, reason: &str) {
+        self.deliveries.push(Delivery::Cancel {
+            reason: reason.to_string(),
+        });
+    }
+}
@@ -1,4 +1,7 @@
-use temporalio_common::protos::temporal::api::{common::v1::Payloads, failure::v1::Failure};
+use temporalio_common::protos::temporal::api::{
+    common::v1::Payloads,
+    failure::v1::{Failure, failure::FailureInfo},
+};
 
 /// The outcome of running a workflow in the test harness.
 ///
@@ -19,6 +22,16 @@ pub struct WorkflowFailure {
     pub is_wft_failure: bool,
 }
 
+impl WorkflowFailure {
+    /// `true` when the workflow finished by accepting a cancellation request.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(
+            self.failure.as_ref().and_then(|f| f.failure_info.as_ref()),
+            Some(FailureInfo::CanceledFailureInfo(_))
+        )
+    }
+}
+
 impl std::fmt::Display for WorkflowFailure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "WorkflowFailure: {}", self.message)
@@ -35,6 +48,36 @@ pub enum TestHarnessError {
 
     #[error("worker returned an error: {0}")]
     WorkerError(String),
+
+    #[error("workflow scheduled activity `{0}` but no mock was registered for it")]
+    UnmockedActivity(String),
+
+    #[error("workflow scheduled activity `{activity}` with unexpected input: {details}")]
+    InputMismatch { activity: String, details: String },
+
+    #[error(
+        "activity `{activity}` was still being retried after {attempts} attempts; its retry \
+         policy never gives up on the mocked failure"
+    )]
+    EndlessRetries { activity: String, attempts: u32 },
+
+    #[error("workflow started child workflow `{0}` but no mock was registered for it")]
+    UnmockedChildWorkflow(String),
+
+    #[error("child workflow `{workflow_type}` could not be run: {source}")]
+    ChildWorkflow {
+        workflow_type: String,
+        source: Box<TestHarnessError>,
+    },
+
+    #[error("workflow issued a command the harness does not support: {0}")]
+    UnsupportedCommand(String),
+
+    #[error("workflow has not been executed yet")]
+    NotExecuted,
+
+    #[error("query `{query}` failed: {message}")]
+    QueryFailed { query: String, message: String },
 }
 
 /// Errors returned by [`TestWorkflowEnvironment::workflow_result`].
@@ -43,9 +86,26 @@ pub enum WorkflowResultError {
     #[error("workflow has not been executed yet")]
     NotExecuted,
     #[error("workflow completed with error: {0}")]
-    WorkflowFailed(WorkflowFailure),
+    WorkflowFailed(Box<WorkflowFailure>),
     #[error("workflow produced no result payload")]
     NoResult,
     #[error("failed to deserialize result: {0}")]
     DeserializeError(String),
 }
+
+/// Errors returned by [`UpdateOutcome::result`].
+///
+/// [`UpdateOutcome::result`]: crate::UpdateOutcome::result
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateResultError {
+    #[error("update was rejected: {0}")]
+    Rejected(String),
+    #[error("update handler had not returned when the workflow finished")]
+    NotCompleted,
+    #[error("update handler failed: {0}")]
+    Failed(String),
+    #[error("update handler produced no result payload")]
+    NoResult,
+    #[error("failed to deserialize update result: {0}")]
+    DeserializeError(String),
+}
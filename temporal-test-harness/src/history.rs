@@ -1,90 +1,469 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
 use temporalio_common::protos::{
     coresdk::IntoPayloadsExt,
     temporal::api::{
-        common::v1::{ActivityType, Payload, Payloads},
+        command::v1::{
+            ModifyWorkflowPropertiesCommandAttributes, RecordMarkerCommandAttributes,
+            RequestCancelActivityTaskCommandAttributes, ScheduleActivityTaskCommandAttributes,
+            StartChildWorkflowExecutionCommandAttributes, StartTimerCommandAttributes,
+            UpsertWorkflowSearchAttributesCommandAttributes,
+        },
+        common::v1::{Payload, Payloads, WorkflowExecution},
         enums::v1::EventType,
-        failure::v1::Failure,
         history::v1::{
-            ActivityTaskCompletedEventAttributes, ActivityTaskFailedEventAttributes,
-            ActivityTaskScheduledEventAttributes, ActivityTaskStartedEventAttributes,
-            history_event::Attributes,
+            ActivityTaskCanceledEventAttributes, ActivityTaskCompletedEventAttributes,
+            ActivityTaskFailedEventAttributes, ActivityTaskScheduledEventAttributes,
+            ActivityTaskStartedEventAttributes, ChildWorkflowExecutionCompletedEventAttributes,
+            ChildWorkflowExecutionFailedEventAttributes,
+            ChildWorkflowExecutionStartedEventAttributes, MarkerRecordedEventAttributes,
+            StartChildWorkflowExecutionInitiatedEventAttributes, TimerCanceledEventAttributes,
+            TimerFiredEventAttributes, TimerStartedEventAttributes,
+            UpsertWorkflowSearchAttributesEventAttributes,
+            WorkflowExecutionCancelRequestedEventAttributes,
+            WorkflowExecutionUpdateAcceptedEventAttributes,
+            WorkflowExecutionUpdateCompletedEventAttributes,
+            WorkflowPropertiesModifiedEventAttributes, history_event::Attributes,
         },
+        protocol::v1::{Message, message::SequencingId},
+        query::v1::WorkflowQuery,
+        sdk::v1::WorkflowTaskCompletedMetadata,
+        update::v1::{Acceptance, Input, Meta, Request, Response},
+        workflowservice::v1::PollWorkflowTaskQueueResponse,
     },
+    utilities::pack_any,
 };
-use temporalio_sdk_core::replay::TestHistoryBuilder;
-
-/// A single mocked activity result.
-pub enum ActivityMock {
-    /// The activity completed successfully with the given JSON-serializable payload.
-    Success(Payload),
-    /// The activity failed with the given error message.
-    Failure(String),
-}
+use temporalio_sdk_core::{
+    replay::TestHistoryBuilder,
+    test_help::{ResponseType, hist_to_poll_resp},
+};
+
+use crate::child::{ChildOutcome, StartedChild};
+use crate::retry::{ActivityMock, ActivityResolution};
+
+/// Workflow id used for every synthetic execution.
+const WORKFLOW_ID: &str = "fake_wf_id";
 
-/// Build a synthetic workflow history from activity mocks.
+/// A workflow history that grows one workflow task at a time, driven by the
+/// commands the workflow under test actually issues.
 ///
-/// Returns `(history_builder, has_failure)` where `has_failure` is true if any
-/// activity was mocked as a failure (stops adding activities after the first failure).
-pub fn build_history(
-    workflow_type: &str,
-    input_payloads: Payloads,
-    activity_mocks: &[(String, ActivityMock)],
-) -> (TestHistoryBuilder, bool) {
-    let mut t = TestHistoryBuilder::default();
-    t.add_by_type(EventType::WorkflowExecutionStarted);
-    t.set_wf_type(workflow_type);
-    t.set_wf_input(input_payloads);
-
-    let mut has_failure = false;
-    for (i, (activity_name, mock)) in activity_mocks.iter().enumerate() {
-        let activity_id = (i + 1).to_string();
-
-        // Add a full WFT before each activity (workflow task scheduled + started + completed)
-        t.add_full_wf_task();
-
-        let scheduled_event_id = t.add(ActivityTaskScheduledEventAttributes {
-            activity_id: activity_id.clone(),
-            activity_type: Some(ActivityType {
-                name: activity_name.clone(),
-            }),
+/// Each call to [`SyntheticHistory::poll_response`] hands the mock worker the whole
+/// history so far, ending in a started workflow task.
+///
+/// Events are timestamped on a virtual clock that only moves when [`advance`] is called,
+/// so the workflow sees time pass without the test waiting for it.
+///
+/// [`advance`]: SyntheticHistory::advance
+pub(crate) struct SyntheticHistory {
+    t: TestHistoryBuilder,
+    /// Id of the most recent `WorkflowTaskCompleted` event; command events point back at it.
+    workflow_task_completed_event_id: i64,
+    start_time: SystemTime,
+    /// Virtual time since the workflow started.
+    elapsed: Duration,
+    /// Id of the last event given a virtual timestamp.
+    stamped_event_id: i64,
+    /// Update requests to hand the worker with the next workflow task.
+    messages: Vec<Message>,
+    /// The history as the worker was last handed it.
+    polled: TestHistoryBuilder,
+    /// Histories to replay to query the workflow, oldest first, each paired with the
+    /// virtual time its last workflow task ran at.
+    checkpoints: Vec<(Duration, TestHistoryBuilder)>,
+}
+
+impl SyntheticHistory {
+    /// Start a history with `WorkflowExecutionStarted` and the first workflow task, both
+    /// at `start_time`.
+    pub(crate) fn new(
+        workflow_type: &str,
+        input_payloads: Payloads,
+        start_time: SystemTime,
+    ) -> Self {
+        let mut t = TestHistoryBuilder::default();
+        t.add_by_type(EventType::WorkflowExecutionStarted);
+        t.set_wf_type(workflow_type);
+        t.set_wf_input(input_payloads);
+        t.add_workflow_task_scheduled_and_started();
+        Self {
+            t,
+            workflow_task_completed_event_id: 0,
+            start_time,
+            elapsed: Duration::ZERO,
+            stamped_event_id: 0,
+            messages: Vec::new(),
+            polled: TestHistoryBuilder::default(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// A poll response carrying the full history up to the latest started workflow task,
+    /// and any update requests made since the last one.
+    pub(crate) fn poll_response(&mut self) -> PollWorkflowTaskQueueResponse {
+        self.stamp();
+        self.polled = self.t.clone();
+        let mut resp = hist_to_poll_resp(&self.t, WORKFLOW_ID, ResponseType::AllHistory).resp;
+        resp.messages = std::mem::take(&mut self.messages);
+        resp
+    }
+
+    /// Remember the history as it stands after the outstanding workflow task's commands,
+    /// so the workflow can later be queried as of this point. Must be called after those
+    /// commands are recorded and before anything else happens.
+    pub(crate) fn checkpoint(&mut self) {
+        let mut t = self.t.clone();
+        t.add_workflow_task_scheduled_and_started();
+        self.checkpoints.push((self.elapsed, t));
+    }
+
+    /// Remember the history as it was polled for the workflow task that finished the
+    /// workflow, since no later task could replay its commands.
+    pub(crate) fn final_checkpoint(&mut self) {
+        self.checkpoints.push((self.elapsed, self.polled.clone()));
+    }
+
+    /// A poll response that replays the workflow up to the last workflow task that ran at
+    /// or before `at`, or up to its end if `at` is `None`, and then asks `queries`.
+    pub(crate) fn query_response(
+        &self,
+        at: Option<Duration>,
+        queries: HashMap<String, WorkflowQuery>,
+    ) -> Option<PollWorkflowTaskQueueResponse> {
+        let (_, t) = self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|(ran_at, _)| at.is_none_or(|at| *ran_at <= at))?;
+        let mut resp = hist_to_poll_resp(t, WORKFLOW_ID, ResponseType::AllHistory).resp;
+        resp.queries = queries;
+        Some(resp)
+    }
+
+    /// Virtual time since the workflow started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The current virtual time.
+    pub(crate) fn now(&self) -> SystemTime {
+        self.start_time + self.elapsed
+    }
+
+    /// Move the virtual clock forward to `elapsed` after the start. Events added from
+    /// now on happen at that time.
+    pub(crate) fn advance(&mut self, elapsed: Duration) {
+        self.stamp();
+        self.elapsed = self.elapsed.max(elapsed);
+    }
+
+    /// Give every event added since the last call the current virtual time.
+    fn stamp(&mut self) {
+        let now = self.now();
+        for event_id in self.stamped_event_id + 1..=self.t.current_event_id() {
+            self.t
+                .modify_event(event_id, |event| event.event_time = Some(now.into()));
+        }
+        self.stamped_event_id = self.t.current_event_id();
+    }
+
+    /// Record that the worker completed the outstanding workflow task, with the SDK flags
+    /// it used, which a replay needs to make the same decisions, e.g. for patches.
+    pub(crate) fn complete_workflow_task(&mut self, sdk_metadata: WorkflowTaskCompletedMetadata) {
+        self.t.add_workflow_task_completed();
+        self.workflow_task_completed_event_id = self.t.current_event_id();
+        self.t
+            .modify_event(self.workflow_task_completed_event_id, |event| {
+                if let Some(Attributes::WorkflowTaskCompletedEventAttributes(attrs)) =
+                    &mut event.attributes
+                {
+                    attrs.sdk_metadata = Some(sdk_metadata);
+                }
+            });
+    }
+
+    /// Schedule and start the next workflow task, so the worker sees what happened since.
+    pub(crate) fn start_workflow_task(&mut self) {
+        self.t.add_workflow_task_scheduled_and_started();
+    }
+
+    /// Add the `ActivityTaskScheduled` event for a `ScheduleActivityTask` command.
+    /// Returns the id of the scheduled event.
+    pub(crate) fn schedule_activity(
+        &mut self,
+        attrs: &ScheduleActivityTaskCommandAttributes,
+    ) -> i64 {
+        self.t.add(ActivityTaskScheduledEventAttributes {
+            activity_id: attrs.activity_id.clone(),
+            activity_type: attrs.activity_type.clone(),
+            task_queue: attrs.task_queue.clone(),
+            header: attrs.header.clone(),
+            input: attrs.input.clone(),
+            schedule_to_close_timeout: attrs.schedule_to_close_timeout,
+            schedule_to_start_timeout: attrs.schedule_to_start_timeout,
+            start_to_close_timeout: attrs.start_to_close_timeout,
+            heartbeat_timeout: attrs.heartbeat_timeout,
+            workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+            retry_policy: attrs.retry_policy.clone(),
             ..Default::default()
-        });
-        let started_event_id = t.add(Attributes::ActivityTaskStartedEventAttributes(
+        })
+    }
+
+    /// Start a scheduled activity on the attempt that settled it, and complete or fail it
+    /// as that attempt did. Earlier attempts leave no events, as on the server.
+    pub(crate) fn resolve_activity(
+        &mut self,
+        scheduled_event_id: i64,
+        resolution: &ActivityResolution,
+    ) {
+        let started_event_id = self.t.add(Attributes::ActivityTaskStartedEventAttributes(
             ActivityTaskStartedEventAttributes {
                 scheduled_event_id,
+                attempt: resolution.attempt as i32,
+                last_failure: resolution.last_failure.clone(),
                 ..Default::default()
             },
         ));
 
-        match mock {
+        match &resolution.outcome {
             ActivityMock::Success(payload) => {
-                t.add(ActivityTaskCompletedEventAttributes {
+                self.t.add(ActivityTaskCompletedEventAttributes {
                     scheduled_event_id,
                     started_event_id,
                     result: vec![payload.clone()].into_payloads(),
                     ..Default::default()
                 });
             }
-            ActivityMock::Failure(message) => {
-                t.add(Attributes::ActivityTaskFailedEventAttributes(
+            ActivityMock::Failure(failure) => {
+                self.t.add(Attributes::ActivityTaskFailedEventAttributes(
                     ActivityTaskFailedEventAttributes {
                         scheduled_event_id,
                         started_event_id,
-                        failure: Some(Failure {
-                            message: message.clone(),
-                            ..Default::default()
-                        }),
+                        failure: Some(failure.to_failure()),
+                        retry_state: resolution.retry_state as i32,
                         ..Default::default()
                     },
                 ));
-                has_failure = true;
-                break; // Stop after first failure
             }
         }
     }
 
-    // Final WFT scheduled + started (for the workflow to process the last result or to complete)
-    t.add_workflow_task_scheduled_and_started();
+    /// Add the `TimerStarted` event for a `StartTimer` command. Returns the id of the
+    /// started event.
+    pub(crate) fn start_timer(&mut self, attrs: &StartTimerCommandAttributes) -> i64 {
+        self.t.add(TimerStartedEventAttributes {
+            timer_id: attrs.timer_id.clone(),
+            start_to_fire_timeout: attrs.start_to_fire_timeout,
+            workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+        })
+    }
+
+    /// Record that a started timer fired.
+    pub(crate) fn fire_timer(&mut self, started_event_id: i64, timer_id: &str) {
+        self.t.add(TimerFiredEventAttributes {
+            timer_id: timer_id.to_string(),
+            started_event_id,
+        });
+    }
+
+    /// Add the `TimerCanceled` event for a `CancelTimer` command.
+    pub(crate) fn cancel_timer(&mut self, started_event_id: i64, timer_id: &str) {
+        self.t.add(TimerCanceledEventAttributes {
+            timer_id: timer_id.to_string(),
+            started_event_id,
+            workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+            ..Default::default()
+        });
+    }
 
-    (t, has_failure)
+    /// Add the `StartChildWorkflowExecutionInitiated` event for a
+    /// `StartChildWorkflowExecution` command. Returns the id of the initiated event.
+    pub(crate) fn initiate_child_workflow(
+        &mut self,
+        attrs: &StartChildWorkflowExecutionCommandAttributes,
+    ) -> i64 {
+        self.t
+            .add(StartChildWorkflowExecutionInitiatedEventAttributes {
+                namespace: attrs.namespace.clone(),
+                workflow_id: attrs.workflow_id.clone(),
+                workflow_type: attrs.workflow_type.clone(),
+                task_queue: attrs.task_queue.clone(),
+                input: attrs.input.clone(),
+                workflow_execution_timeout: attrs.workflow_execution_timeout,
+                workflow_run_timeout: attrs.workflow_run_timeout,
+                workflow_task_timeout: attrs.workflow_task_timeout,
+                parent_close_policy: attrs.parent_close_policy,
+                workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+                workflow_id_reuse_policy: attrs.workflow_id_reuse_policy,
+                retry_policy: attrs.retry_policy.clone(),
+                cron_schedule: attrs.cron_schedule.clone(),
+                header: attrs.header.clone(),
+                memo: attrs.memo.clone(),
+                search_attributes: attrs.search_attributes.clone(),
+                ..Default::default()
+            })
+    }
+
+    /// Record that an initiated child workflow started. Returns the id of the started
+    /// event.
+    pub(crate) fn child_workflow_started(
+        &mut self,
+        initiated_event_id: i64,
+        execution: &WorkflowExecution,
+        attrs: &StartChildWorkflowExecutionCommandAttributes,
+    ) -> i64 {
+        self.t.add(ChildWorkflowExecutionStartedEventAttributes {
+            namespace: attrs.namespace.clone(),
+            initiated_event_id,
+            workflow_execution: Some(execution.clone()),
+            workflow_type: attrs.workflow_type.clone(),
+            header: attrs.header.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Record how a started child workflow finished.
+    pub(crate) fn child_workflow_finished(&mut self, child: &StartedChild) {
+        match &child.outcome {
+            ChildOutcome::Completed(result) => {
+                self.t.add(ChildWorkflowExecutionCompletedEventAttributes {
+                    result: result.clone(),
+                    workflow_execution: Some(child.execution.clone()),
+                    workflow_type: child.workflow_type.clone(),
+                    initiated_event_id: child.initiated_event_id,
+                    started_event_id: child.started_event_id,
+                    ..Default::default()
+                });
+            }
+            ChildOutcome::Failed(failure) => {
+                self.t.add(ChildWorkflowExecutionFailedEventAttributes {
+                    failure: Some(failure.as_ref().clone()),
+                    workflow_execution: Some(child.execution.clone()),
+                    workflow_type: child.workflow_type.clone(),
+                    initiated_event_id: child.initiated_event_id,
+                    started_event_id: child.started_event_id,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Add the event for an `UpsertWorkflowSearchAttributes` command.
+    pub(crate) fn upsert_search_attributes(
+        &mut self,
+        attrs: &UpsertWorkflowSearchAttributesCommandAttributes,
+    ) {
+        self.t.add(UpsertWorkflowSearchAttributesEventAttributes {
+            workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+            search_attributes: attrs.search_attributes.clone(),
+        });
+    }
+
+    /// Add the event for a `ModifyWorkflowProperties` command (memo upserts).
+    pub(crate) fn modify_workflow_properties(
+        &mut self,
+        attrs: &ModifyWorkflowPropertiesCommandAttributes,
+    ) {
+        self.t.add(WorkflowPropertiesModifiedEventAttributes {
+            workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+            upserted_memo: attrs.upserted_memo.clone(),
+        });
+    }
+
+    /// Add the event for a `RecordMarker` command, e.g. a completed local activity.
+    pub(crate) fn record_marker(&mut self, attrs: &RecordMarkerCommandAttributes) {
+        self.t.add(MarkerRecordedEventAttributes {
+            marker_name: attrs.marker_name.clone(),
+            details: attrs.details.clone(),
+            workflow_task_completed_event_id: self.workflow_task_completed_event_id,
+            header: attrs.header.clone(),
+            failure: attrs.failure.clone(),
+        });
+    }
+
+    /// Add the event for a `RequestCancelActivityTask` command.
+    /// Returns the id of the cancel-requested event.
+    pub(crate) fn request_cancel_activity(
+        &mut self,
+        attrs: &RequestCancelActivityTaskCommandAttributes,
+    ) -> i64 {
+        self.t
+            .add_activity_task_cancel_requested(attrs.scheduled_event_id);
+        self.t.current_event_id()
+    }
+
+    /// Record that an activity was cancelled while waiting to retry, before another
+    /// attempt started.
+    pub(crate) fn activity_cancelled(
+        &mut self,
+        scheduled_event_id: i64,
+        cancel_requested_event_id: i64,
+    ) {
+        self.t.add(ActivityTaskCanceledEventAttributes {
+            scheduled_event_id,
+            latest_cancel_requested_event_id: cancel_requested_event_id,
+            ..Default::default()
+        });
+    }
+
+    /// Record a signal sent to the workflow.
+    pub(crate) fn signaled(&mut self, signal_name: &str, input: Payload) {
+        self.t.add_we_signaled(signal_name, vec![input]);
+    }
+
+    /// Ask the workflow to run the update `name`, in the next workflow task.
+    pub(crate) fn request_update(&mut self, update_id: &str, name: &str, input: Payload) {
+        let request = Request {
+            meta: Some(Meta {
+                update_id: update_id.to_string(),
+                identity: "test-harness".to_string(),
+            }),
+            input: Some(Input {
+                header: None,
+                name: name.to_string(),
+                args: vec![input].into_payloads(),
+            }),
+        };
+        self.messages.push(Message {
+            id: format!("{update_id}/request"),
+            protocol_instance_id: update_id.to_string(),
+            sequencing_id: Some(SequencingId::EventId(self.t.current_event_id())),
+            body: Some(
+                pack_any(
+                    "type.googleapis.com/temporal.api.update.v1.Request".to_string(),
+                    &request,
+                )
+                .expect("update request must encode"),
+            ),
+        });
+    }
+
+    /// Add the `WorkflowExecutionUpdateAccepted` event for an update the workflow
+    /// accepted. Returns the id of the accepted event.
+    pub(crate) fn update_accepted(&mut self, update_id: &str, acceptance: Acceptance) -> i64 {
+        self.t.add(WorkflowExecutionUpdateAcceptedEventAttributes {
+            protocol_instance_id: update_id.to_string(),
+            accepted_request_message_id: acceptance.accepted_request_message_id,
+            accepted_request_sequencing_event_id: acceptance.accepted_request_sequencing_event_id,
+            accepted_request: acceptance.accepted_request,
+        })
+    }
+
+    /// Add the `WorkflowExecutionUpdateCompleted` event for an accepted update whose
+    /// handler returned.
+    pub(crate) fn update_completed(&mut self, accepted_event_id: i64, response: Response) {
+        self.t.add(WorkflowExecutionUpdateCompletedEventAttributes {
+            meta: response.meta,
+            accepted_event_id,
+            outcome: response.outcome,
+        });
+    }
+
+    /// Record that someone asked for the workflow to be cancelled.
+    pub(crate) fn cancel_requested(&mut self, reason: &str) {
+        self.t.add(WorkflowExecutionCancelRequestedEventAttributes {
+            cause: reason.to_string(),
+            ..Default::default()
+        });
+    }
 }
@@ -1,6 +1,16 @@
+mod calls;
+mod child;
+mod delivery;
 mod error;
 mod history;
+mod matcher;
+mod retry;
 mod runner;
+mod update;
 
-pub use error::{TestHarnessError, WorkflowFailure, WorkflowResultError};
+pub use calls::ActivityCall;
+pub use child::ChildWorkflowMockCall;
+pub use delivery::CallbackContext;
+pub use error::{TestHarnessError, UpdateResultError, WorkflowFailure, WorkflowResultError};
 pub use runner::TestWorkflowEnvironment;
+pub use update::{UpdateHandle, UpdateOutcome};
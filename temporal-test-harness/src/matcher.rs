@@ -0,0 +1,105 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use temporalio_common::protos::temporal::api::common::v1::Payload;
+
+type Predicate = Box<dyn Fn(&Value) -> Result<bool, String> + Send + Sync>;
+
+/// What a mocked activity expects the workflow to pass it.
+pub(crate) enum InputMatcher {
+    /// The input must serialize to exactly this JSON value.
+    Equals(Value),
+    /// The input must deserialize into the predicate's argument type and satisfy it.
+    Matching(Predicate),
+}
+
+impl InputMatcher {
+    pub(crate) fn equals(value: Value) -> Self {
+        Self::Equals(value)
+    }
+
+    pub(crate) fn matching<T, F>(predicate: F) -> Self
+    where
+        T: DeserializeOwned,
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        Self::Matching(Box::new(move |input| {
+            Ok(predicate(&deserialize_input::<T>(input)?))
+        }))
+    }
+
+    /// `Ok` if `input` is what this matcher expects, otherwise an explanation of why not.
+    pub(crate) fn check(&self, input: &Value) -> Result<(), String> {
+        match self {
+            Self::Equals(expected) if expected == input => Ok(()),
+            Self::Equals(expected) => {
+                let mut diff = String::new();
+                json_diff("input", expected, input, &mut diff);
+                Err(format!("(- expected, + actual)\n{diff}"))
+            }
+            Self::Matching(predicate) => match predicate(input)? {
+                true => Ok(()),
+                false => Err(format!(
+                    "it doesn't satisfy the predicate:\n{}",
+                    pretty(input)
+                )),
+            },
+        }
+    }
+}
+
+/// The JSON value of an activity's first input payload, or `null` if it had none.
+pub(crate) fn input_value(payload: Option<&Payload>) -> Value {
+    payload
+        .and_then(|payload| serde_json::from_slice(&payload.data).ok())
+        .unwrap_or(Value::Null)
+}
+
+/// Deserialize an activity's input into the type a test's closure takes.
+pub(crate) fn deserialize_input<T: DeserializeOwned>(input: &Value) -> Result<T, String> {
+    T::deserialize(input).map_err(|e| {
+        format!(
+            "can't deserialize it as {}: {e}",
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Append a line-per-difference diff of `expected` against `actual` to `out`, naming
+/// each differing field by its path from the root.
+fn json_diff(path: &str, expected: &Value, actual: &Value, out: &mut String) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let path = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(other) => json_diff(&path, value, other, out),
+                    None => out.push_str(&format!("- {path}: {value}\n")),
+                }
+            }
+            for (key, value) in actual {
+                if !expected.contains_key(key) {
+                    out.push_str(&format!("+ {path}.{key}: {value}\n"));
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for i in 0..expected.len().max(actual.len()) {
+                let path = format!("{path}[{i}]");
+                match (expected.get(i), actual.get(i)) {
+                    (Some(value), Some(other)) => json_diff(&path, value, other, out),
+                    (Some(value), None) => out.push_str(&format!("- {path}: {value}\n")),
+                    (None, Some(other)) => out.push_str(&format!("+ {path}: {other}\n")),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if expected != actual => {
+            out.push_str(&format!("- {path}: {expected}\n+ {path}: {actual}\n"));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use temporalio_common::protos::temporal::api::{
+    common::v1::{Payload, RetryPolicy},
+    enums::v1::RetryState,
+    failure::v1::{ApplicationFailureInfo, Failure, failure::FailureInfo},
+};
+
+/// The most attempts the harness simulates for one call before concluding the retry
+/// policy will never give up.
+pub(crate) const MAX_SIMULATED_ATTEMPTS: u32 = 1000;
+
+/// How a single attempt at an activity went.
+#[derive(Clone)]
+pub enum ActivityMock {
+    /// The activity completed successfully with the given JSON-serializable payload.
+    Success(Payload),
+    /// The attempt failed.
+    Failure(MockFailure),
+}
+
+/// An application error a mocked activity attempt failed with.
+#[derive(Clone)]
+pub struct MockFailure {
+    pub(crate) message: String,
+    /// Checked against the retry policy's `non_retryable_error_types`.
+    pub(crate) error_type: String,
+    /// Set when the activity itself ruled out retries, whatever the policy says.
+    pub(crate) non_retryable: bool,
+}
+
+impl MockFailure {
+    /// A retryable failure with no error type.
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            error_type: String::new(),
+            non_retryable: false,
+        }
+    }
+
+    pub(crate) fn of_type(error_type: &str, message: impl Into<String>) -> Self {
+        Self {
+            error_type: error_type.to_string(),
+            ..Self::new(message)
+        }
+    }
+
+    pub(crate) fn non_retryable(message: impl Into<String>) -> Self {
+        Self {
+            non_retryable: true,
+            ..Self::new(message)
+        }
+    }
+
+    /// The failure as the server records it.
+    pub(crate) fn to_failure(&self) -> Failure {
+        Failure {
+            message: self.message.clone(),
+            failure_info: Some(FailureInfo::ApplicationFailureInfo(
+                ApplicationFailureInfo {
+                    r#type: self.error_type.clone(),
+                    non_retryable: self.non_retryable,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+/// How an activity call played out across its attempts.
+pub(crate) struct ActivityResolution {
+    /// The attempt the call finished on, counting from 1.
+    pub(crate) attempt: u32,
+    /// How that attempt went.
+    pub(crate) outcome: ActivityMock,
+    /// The failure of the attempt before it, if it was a retry.
+    pub(crate) last_failure: Option<Failure>,
+    /// Why the server stopped retrying, if the call failed.
+    pub(crate) retry_state: RetryState,
+    /// Time spent backing off between attempts.
+    pub(crate) backoff: Duration,
+}
+
+/// Run `attempt` for attempts 1, 2, ... until one succeeds or `policy` gives up on a
+/// failure, backing off between them as the server would.
+pub(crate) fn resolve<E>(
+    policy: Option<&RetryPolicy>,
+    mut attempt: impl FnMut(u32) -> Result<ActivityMock, E>,
+) -> Result<ActivityResolution, RetryError<E>> {
+    let mut backoff = Duration::ZERO;
+    let mut last_failure = None;
+    for number in 1..=MAX_SIMULATED_ATTEMPTS {
+        let outcome = attempt(number).map_err(RetryError::Attempt)?;
+        let retry_state = match &outcome {
+            ActivityMock::Success(_) => RetryState::Unspecified,
+            ActivityMock::Failure(failure) => match gives_up(policy, number, failure) {
+                Some(retry_state) => retry_state,
+                None => {
+                    backoff += interval(policy, number);
+                    last_failure = Some(failure.to_failure());
+                    continue;
+                }
+            },
+        };
+        return Ok(ActivityResolution {
+            attempt: number,
+            outcome,
+            last_failure,
+            retry_state,
+            backoff,
+        });
+    }
+    Err(RetryError::Endless)
+}
+
+/// Why [`resolve`] couldn't settle a call.
+pub(crate) enum RetryError<E> {
+    /// An attempt couldn't be made.
+    Attempt(E),
+    /// The policy was still retrying after [`MAX_SIMULATED_ATTEMPTS`].
+    Endless,
+}
+
+/// Why `policy` stops after `attempt` failed with `failure`, or `None` if it retries.
+fn gives_up(
+    policy: Option<&RetryPolicy>,
+    attempt: u32,
+    failure: &MockFailure,
+) -> Option<RetryState> {
+    let non_retryable_types = policy.map_or(&[][..], |p| &p.non_retryable_error_types[..]);
+    if failure.non_retryable || non_retryable_types.contains(&failure.error_type) {
+        return Some(RetryState::NonRetryableFailure);
+    }
+    let maximum_attempts = policy.map_or(0, |p| p.maximum_attempts);
+    if maximum_attempts > 0 && attempt >= maximum_attempts as u32 {
+        return Some(RetryState::MaximumAttemptsReached);
+    }
+    None
+}
+
+/// How long the server waits after `attempt` fails before the next one, with the
+/// server's defaults for anything `policy` leaves unset: a 1s initial interval, a
+/// backoff coefficient of 2, and a maximum of 100 times the initial interval.
+fn interval(policy: Option<&RetryPolicy>, attempt: u32) -> Duration {
+    let initial = policy
+        .and_then(|p| duration(p.initial_interval))
+        .filter(|d| !d.is_zero())
+        .unwrap_or(Duration::from_secs(1));
+    let coefficient = policy
+        .map(|p| p.backoff_coefficient)
+        .filter(|c| *c >= 1.0)
+        .unwrap_or(2.0);
+    let maximum = policy
+        .and_then(|p| duration(p.maximum_interval))
+        .filter(|d| !d.is_zero())
+        .unwrap_or(initial * 100);
+    let backed_off = initial.as_secs_f64() * coefficient.powi(attempt as i32 - 1);
+    Duration::from_secs_f64(backed_off.min(maximum.as_secs_f64()))
+}
+
+fn duration<D: TryInto<Duration>>(d: Option<D>) -> Option<Duration> {
+    d.and_then(|d| d.try_into().ok())
+}
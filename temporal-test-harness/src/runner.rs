@@ -1,37 +1,267 @@
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use futures::StreamExt;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use temporalio_common::{
     data_converters::DataConverter,
     protos::{
-        coresdk::{AsJsonPayloadExt, FromJsonPayloadExt},
-        temporal::api::{command::v1::command, common::v1::Payloads, failure::v1::Failure},
+        coresdk::{
+            AsJsonPayloadExt, FromJsonPayloadExt,
+            workflow_commands::{QueryResult, query_result},
+        },
+        temporal::api::{
+            command::v1::{Command, command},
+            common::v1::{Payload, Payloads, RetryPolicy, WorkflowExecution},
+            failure::v1::{CanceledFailureInfo, Failure, failure::FailureInfo},
+            protocol::v1::Message,
+            query::v1::WorkflowQuery,
+            sdk::v1::WorkflowTaskCompletedMetadata,
+            update::v1::{Acceptance, Rejection, Response},
+            workflowservice::v1::PollWorkflowTaskQueueResponse,
+        },
     },
 };
 use temporalio_sdk::{
     Worker as SdkWorker, activities::ActivityImplementer, workflows::WorkflowImplementer,
 };
-use temporalio_sdk_core::test_help::{MockPollCfg, build_mock_pollers, mock_worker};
-use tokio::sync::Notify;
+use temporalio_sdk_core::test_help::{MocksHolder, mock_worker, mock_worker_client};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 
+use crate::calls::ActivityCall;
+use crate::child::{ChildWorkflowMockCall, MockedChildWorkflow, StartedChild};
+use crate::delivery::{CallbackContext, Delivery, ScheduledDelivery, Trigger};
 use crate::error::{TestHarnessError, WorkflowFailure, WorkflowResultError, WorkflowTestResult};
-use crate::history::{ActivityMock, build_history};
+use crate::history::SyntheticHistory;
+use crate::matcher::{InputMatcher, deserialize_input, input_value};
+use crate::retry::{self, ActivityMock, ActivityResolution, MockFailure, RetryError};
+use crate::update::{UpdateHandle, UpdateOutcome};
 
-/// Captured result from the mock worker — populated by callbacks.
+/// What the mock worker reported back for a workflow task.
+enum WorkerEvent {
+    /// The workflow task completed with these commands and protocol messages.
+    Completed {
+        commands: Vec<Command>,
+        messages: Vec<Message>,
+        sdk_metadata: WorkflowTaskCompletedMetadata,
+    },
+    /// The workflow task failed (panic, nondeterminism, ...).
+    Failed(Option<Box<Failure>>),
+}
+
+/// A registered activity mock: which calls it answers, and with what.
+struct MockedActivity {
+    name: String,
+    /// Calls whose input this rejects are left for later mocks of the same activity.
+    input: Option<InputMatcher>,
+    /// How the first attempts fail, before `outcome` answers the rest.
+    failing_first: Vec<MockFailure>,
+    outcome: MockOutcome,
+}
+
+impl MockedActivity {
+    /// How attempt number `attempt` at a call with `input` goes.
+    fn attempt(&self, input: &serde_json::Value, attempt: u32) -> Result<ActivityMock, String> {
+        match self.failing_first.get(attempt as usize - 1) {
+            Some(failure) => Ok(ActivityMock::Failure(failure.clone())),
+            None => self.outcome.resolve(input, attempt),
+        }
+    }
+}
+
+type Responder = Box<dyn Fn(&serde_json::Value, u32) -> Result<ActivityMock, String> + Send + Sync>;
+
+/// How a mocked activity answers each attempt at a call.
+enum MockOutcome {
+    /// The same result whatever the input and attempt.
+    Fixed(ActivityMock),
+    /// A result computed from the call's input and attempt number, or why the input
+    /// couldn't be used.
+    Computed(Responder),
+}
+
+impl MockOutcome {
+    fn resolve(&self, input: &serde_json::Value, attempt: u32) -> Result<ActivityMock, String> {
+        match self {
+            Self::Fixed(mock) => Ok(mock.clone()),
+            Self::Computed(respond) => respond(input, attempt),
+        }
+    }
+}
+
+/// The activity mocks of an environment, and what the workflow asked of them.
 #[derive(Default)]
-struct CapturedResult {
-    /// Set when a `CompleteWorkflowExecution` command is observed.
-    success: Option<Option<Payloads>>,
-    /// Set when a `FailWorkflowExecution` command is observed.
-    command_failure: Option<WorkflowFailure>,
-    /// Set when a WFT failure is observed (activity error propagated via `?`).
-    wft_failure: Option<WorkflowFailure>,
+struct Mocks {
+    /// Mocks no call has used yet, in the order they were registered.
+    unused: Vec<MockedActivity>,
+    /// Child workflow mocks no child has used yet, in the order they were registered.
+    unused_children: Vec<MockedChildWorkflow>,
+    /// Every activity the workflow scheduled, in order.
+    calls: Vec<ActivityCall>,
+    /// Why each call or child workflow that no mock answered was turned away.
+    unexpected: Vec<String>,
+}
+
+impl Mocks {
+    /// Use up the first unused mock of `name` that accepts `input`, and resolve the call
+    /// with it, retrying failed attempts as `retry_policy` says.
+    fn answer(
+        &mut self,
+        name: &str,
+        input: &serde_json::Value,
+        retry_policy: Option<&RetryPolicy>,
+    ) -> Result<ActivityResolution, TestHarnessError> {
+        let outcome = self.take(name, input).and_then(|mock| {
+            retry::resolve(retry_policy, |attempt| mock.attempt(input, attempt)).map_err(
+                |e| match e {
+                    RetryError::Attempt(details) => TestHarnessError::InputMismatch {
+                        activity: name.to_string(),
+                        details,
+                    },
+                    RetryError::Endless => TestHarnessError::EndlessRetries {
+                        activity: name.to_string(),
+                        attempts: retry::MAX_SIMULATED_ATTEMPTS,
+                    },
+                },
+            )
+        });
+        if let Err(e) = &outcome {
+            self.unexpected.push(e.to_string());
+        }
+        outcome
+    }
+
+    /// Use up the first unused mock of the child workflow type `workflow_type`.
+    fn take_child(&mut self, workflow_type: &str) -> Result<MockedChildWorkflow, TestHarnessError> {
+        match self
+            .unused_children
+            .iter()
+            .position(|mock| mock.workflow_type == workflow_type)
+        {
+            Some(index) => Ok(self.unused_children.remove(index)),
+            None => {
+                let e = TestHarnessError::UnmockedChildWorkflow(workflow_type.to_string());
+                self.unexpected.push(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    fn take(
+        &mut self,
+        name: &str,
+        input: &serde_json::Value,
+    ) -> Result<MockedActivity, TestHarnessError> {
+        let accepts = |mock: &MockedActivity| {
+            mock.input
+                .as_ref()
+                .map_or(Ok(()), |matcher| matcher.check(input))
+        };
+        let mut candidates = self
+            .unused
+            .iter()
+            .enumerate()
+            .filter(|(_, mock)| mock.name == name)
+            .peekable();
+        let Some(&(_, first)) = candidates.peek() else {
+            return Err(TestHarnessError::UnmockedActivity(name.to_string()));
+        };
+        let first_rejection = accepts(first);
+        match candidates.find(|(_, mock)| accepts(mock).is_ok()) {
+            Some((index, _)) => Ok(self.unused.remove(index)),
+            None => Err(TestHarnessError::InputMismatch {
+                activity: name.to_string(),
+                details: first_rejection.unwrap_err(),
+            }),
+        }
+    }
+}
+
+/// An activity call the harness has settled, waiting for the virtual clock to reach the
+/// end of its retries.
+struct PendingActivity {
+    scheduled_event_id: i64,
+    name: String,
+    resolution: ActivityResolution,
+    /// Virtual time after the workflow's start at which the settling attempt finishes.
+    finishes_at: Duration,
+}
+
+/// A timer the workflow started that has neither fired nor been cancelled.
+struct PendingTimer {
+    timer_id: String,
+    started_event_id: i64,
+    /// Virtual time after the workflow's start at which the timer fires.
+    fires_at: Duration,
+}
+
+/// Everything observed while driving one workflow execution.
+struct Execution {
+    history: SyntheticHistory,
+    search_attributes: HashMap<String, Payload>,
+    memo: HashMap<String, Payload>,
+    /// How the workflow answered each update, by update id.
+    updates: HashMap<String, UpdateOutcome>,
+    /// The `WorkflowExecutionUpdateAccepted` event of each accepted update, by update id.
+    accepted_event_ids: HashMap<String, i64>,
+}
+
+impl Execution {
+    fn new(history: SyntheticHistory) -> Self {
+        Self {
+            history,
+            search_attributes: HashMap::new(),
+            memo: HashMap::new(),
+            updates: HashMap::new(),
+            accepted_event_ids: HashMap::new(),
+        }
+    }
+
+    /// Record the workflow's answer to an update, adding the event an acceptance or
+    /// response leads to.
+    fn answer_update(&mut self, message: &Message) -> Result<(), TestHarnessError> {
+        let Some(body) = &message.body else {
+            return Ok(());
+        };
+        let update_id = &message.protocol_instance_id;
+        let undecodable =
+            |e| TestHarnessError::WorkerError(format!("can't decode update message: {e}"));
+        let outcome = match body.type_url.rsplit('.').next() {
+            Some("Acceptance") => {
+                let acceptance = body.to_msg::<Acceptance>().map_err(undecodable)?;
+                let accepted_event_id = self.history.update_accepted(update_id, acceptance);
+                self.accepted_event_ids
+                    .insert(update_id.clone(), accepted_event_id);
+                UpdateOutcome::Accepted
+            }
+            Some("Rejection") => {
+                let rejection = body.to_msg::<Rejection>().map_err(undecodable)?;
+                UpdateOutcome::Rejected(rejection.failure.unwrap_or_default())
+            }
+            Some("Response") => {
+                let response = body.to_msg::<Response>().map_err(undecodable)?;
+                if let Some(&accepted_event_id) = self.accepted_event_ids.get(update_id) {
+                    self.history
+                        .update_completed(accepted_event_id, response.clone());
+                }
+                UpdateOutcome::from_response(response)
+            }
+            _ => return Err(TestHarnessError::UnsupportedCommand(format!("{message:?}"))),
+        };
+        self.updates.insert(update_id.clone(), outcome);
+        Ok(())
+    }
 }
 
-// Type-erased closures for registering workflows/activities on an SdkWorker.
-type WorkflowRegistrar = Box<dyn FnOnce(&mut SdkWorker) + Send>;
+/// The id the harness gives the one query it sends with a replayed workflow task.
+const QUERY_ID: &str = "harness-query";
+
+// Type-erased closures for registering workflows/activities on an SdkWorker. Workflows
+// are registered again for every query, which replays them.
+type WorkflowRegistrar = Arc<dyn Fn(&mut SdkWorker) + Send + Sync>;
 type ActivityRegistrar = Box<dyn FnOnce(&mut SdkWorker) + Send>;
 
 /// A mutable test environment that mirrors Go's `TestWorkflowEnvironment`.
@@ -49,13 +279,32 @@ type ActivityRegistrar = Box<dyn FnOnce(&mut SdkWorker) + Send>;
 /// assert!(env.workflow_error().is_none());
 /// let result: String = env.workflow_result().unwrap();
 /// ```
+///
+/// The history is grown one workflow task at a time from the commands the workflow
+/// sends, as a server would, so it only holds the activities the workflow actually
+/// schedules. Each call is answered by a mock of its activity type (see
+/// [`ActivityMockCall`]): mocks of different activities can be registered in any order,
+/// and only the order among one activity's mocks matters.
+///
+/// Activities complete instantly. Timers run on a virtual clock: whenever the workflow
+/// has nothing else to wait for, the clock skips ahead to the next timer or delayed
+/// callback, so a workflow that sleeps for a day finishes in milliseconds. Failed
+/// activity attempts are retried as the activity's retry policy says, and a retried call
+/// resolves once its backoff has passed on the same clock.
+///
+/// Queries are answered after the run by replaying the recorded history, either to its
+/// end or to a point on the virtual clock.
 pub struct TestWorkflowEnvironment {
     activity_registrar: Option<ActivityRegistrar>,
-    activity_mocks: Vec<(String, ActivityMock)>,
+    mocks: Mocks,
+    deliveries: Vec<ScheduledDelivery>,
     timeout: Duration,
+    start_time: SystemTime,
     // Post-execution state
+    workflow_registrar: Option<WorkflowRegistrar>,
     completed: bool,
     result: Option<WorkflowTestResult>,
+    execution: Option<Execution>,
 }
 
 impl TestWorkflowEnvironment {
@@ -63,10 +312,14 @@ impl TestWorkflowEnvironment {
     pub fn new() -> Self {
         Self {
             activity_registrar: None,
-            activity_mocks: Vec::new(),
+            mocks: Mocks::default(),
+            deliveries: Vec::new(),
             timeout: Duration::from_secs(15),
+            start_time: SystemTime::now(),
+            workflow_registrar: None,
             completed: false,
             result: None,
+            execution: None,
         }
     }
 
@@ -77,15 +330,147 @@ impl TestWorkflowEnvironment {
         }));
     }
 
-    /// Begin mocking an activity by name. Call `.returns(val)` or `.returns_err(msg)`
-    /// on the returned handle to complete the mock.
+    /// Begin mocking an activity by name. Optionally narrow it with `.with_input(..)` or
+    /// `.with_input_matching(..)`, and fail its first attempts with `.fails_first(..)`,
+    /// then call one of the `.returns*` methods on the returned handle to complete the
+    /// mock.
     pub fn on_activity(&mut self, name: &str) -> ActivityMockCall<'_> {
         ActivityMockCall {
-            activity_mocks: &mut self.activity_mocks,
+            activity_mocks: &mut self.mocks.unused,
             name: name.to_string(),
+            input: None,
+            failing_first: Vec::new(),
         }
     }
 
+    /// Begin mocking the child workflows of type `W` the workflow starts. Call
+    /// `.returns(val)`, `.returns_err(msg)` or `.executes(configure)` on the returned
+    /// handle to complete the mock.
+    pub fn on_child_workflow<W>(&mut self) -> ChildWorkflowMockCall<'_, W>
+    where
+        W: WorkflowImplementer + 'static,
+        <W::Run as temporalio_common::WorkflowDefinition>::Input: Send,
+    {
+        ChildWorkflowMockCall {
+            child_mocks: &mut self.mocks.unused_children,
+            workflow: PhantomData,
+        }
+    }
+
+    /// Request cancellation of the workflow as soon as the first `activity` call resolves,
+    /// in the same workflow task that delivers its result.
+    pub fn cancel_workflow_after_activity(&mut self, activity: &str, reason: &str) {
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::AfterActivity(activity.to_string()),
+            delivery: Delivery::Cancel {
+                reason: reason.to_string(),
+            },
+        });
+    }
+
+    /// Send the signal `name` as soon as the first `activity` call resolves, in the same
+    /// workflow task that delivers its result.
+    pub fn signal_workflow_after_activity(
+        &mut self,
+        activity: &str,
+        name: &str,
+        input: impl Serialize,
+    ) {
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::AfterActivity(activity.to_string()),
+            delivery: Delivery::signal(name, input),
+        });
+    }
+
+    /// Send the signal `name` once the virtual clock is `delay` past the workflow's start.
+    ///
+    /// The clock only skips ahead while the workflow is blocked, so the signal arrives
+    /// after every activity scheduled before then has resolved.
+    pub fn signal_workflow_at(&mut self, delay: Duration, name: &str, input: impl Serialize) {
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::At(delay),
+            delivery: Delivery::signal(name, input),
+        });
+    }
+
+    /// Request cancellation of the workflow once the virtual clock is `delay` past its
+    /// start.
+    pub fn cancel_workflow_at(&mut self, delay: Duration, reason: &str) {
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::At(delay),
+            delivery: Delivery::Cancel {
+                reason: reason.to_string(),
+            },
+        });
+    }
+
+    /// Run `callback` once the virtual clock is `delay` past the workflow's start, and
+    /// deliver whatever signals or cancellation it asks for in the next workflow task.
+    pub fn register_delayed_callback(
+        &mut self,
+        delay: Duration,
+        callback: impl FnOnce(&mut CallbackContext) + Send + 'static,
+    ) {
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::At(delay),
+            delivery: Delivery::Callback(Box::new(callback)),
+        });
+    }
+
+    /// Send the update `name` as soon as the workflow first blocks with no activity in
+    /// flight. Look up how it went with [`update_outcome`] once the workflow has run.
+    ///
+    /// [`update_outcome`]: TestWorkflowEnvironment::update_outcome
+    pub fn update_workflow(&mut self, name: &str, input: impl Serialize) -> UpdateHandle {
+        self.update_workflow_at(Duration::ZERO, name, input)
+    }
+
+    /// Send the update `name` once the virtual clock is `delay` past the workflow's start.
+    pub fn update_workflow_at(
+        &mut self,
+        delay: Duration,
+        name: &str,
+        input: impl Serialize,
+    ) -> UpdateHandle {
+        let handle = UpdateHandle::new();
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::At(delay),
+            delivery: Delivery::update(handle.clone(), name, input),
+        });
+        handle
+    }
+
+    /// Send the update `name` as soon as the first `activity` call resolves, in the same
+    /// workflow task that delivers its result.
+    pub fn update_workflow_after_activity(
+        &mut self,
+        activity: &str,
+        name: &str,
+        input: impl Serialize,
+    ) -> UpdateHandle {
+        let handle = UpdateHandle::new();
+        self.deliveries.push(ScheduledDelivery {
+            trigger: Trigger::AfterActivity(activity.to_string()),
+            delivery: Delivery::update(handle.clone(), name, input),
+        });
+        handle
+    }
+
+    /// Start the workflow at `start_time` on the virtual clock instead of the real time.
+    pub fn set_start_time(&mut self, start_time: SystemTime) {
+        self.start_time = start_time;
+    }
+
+    /// The virtual time: the workflow's start time until it has run, and the time it
+    /// finished at afterwards.
+    pub fn now(&self) -> SystemTime {
+        self.start_time
+            + self
+                .execution
+                .as_ref()
+                .map_or(Duration::ZERO, |execution| execution.history.elapsed())
+    }
+
     /// Execute the workflow, storing the result internally.
     ///
     /// After this returns, use [`is_workflow_completed`], [`workflow_error`], and
@@ -99,7 +484,7 @@ impl TestWorkflowEnvironment {
         <W::Run as temporalio_common::WorkflowDefinition>::Input: Send,
     {
         let workflow_name = W::name().to_string();
-        let workflow_registrar: WorkflowRegistrar = Box::new(|worker: &mut SdkWorker| {
+        let workflow_registrar: WorkflowRegistrar = Arc::new(|worker: &mut SdkWorker| {
             worker.register_workflow::<W>();
         });
 
@@ -110,21 +495,100 @@ impl TestWorkflowEnvironment {
             payloads: vec![payload],
         };
 
-        let result = execute_internal(
-            &workflow_name,
-            workflow_registrar,
+        let (result, execution) = execute_internal(
+            SyntheticHistory::new(&workflow_name, input_payloads, self.start_time),
+            &workflow_registrar,
             self.activity_registrar.take(),
-            input_payloads,
-            &self.activity_mocks,
+            &mut self.mocks,
+            std::mem::take(&mut self.deliveries),
             self.timeout,
         )
         .await?;
 
+        self.workflow_registrar = Some(workflow_registrar);
         self.completed = true;
         self.result = Some(result);
+        self.execution = Some(execution);
         Ok(())
     }
 
+    /// Run the query `name` against the workflow's state at the end of the execution.
+    pub async fn query_workflow<T: DeserializeOwned>(
+        &self,
+        name: &str,
+        input: impl Serialize,
+    ) -> Result<T, TestHarnessError> {
+        self.query(None, name, input).await
+    }
+
+    /// Run the query `name` against the workflow's state as it was once the virtual clock
+    /// was `at` past its start, i.e. after the last workflow task that ran by then.
+    pub async fn query_workflow_at<T: DeserializeOwned>(
+        &self,
+        at: Duration,
+        name: &str,
+        input: impl Serialize,
+    ) -> Result<T, TestHarnessError> {
+        self.query(Some(at), name, input).await
+    }
+
+    async fn query<T: DeserializeOwned>(
+        &self,
+        at: Option<Duration>,
+        name: &str,
+        input: impl Serialize,
+    ) -> Result<T, TestHarnessError> {
+        let (Some(execution), Some(workflow_registrar)) =
+            (&self.execution, &self.workflow_registrar)
+        else {
+            return Err(TestHarnessError::NotExecuted);
+        };
+        let failed = |message: String| TestHarnessError::QueryFailed {
+            query: name.to_string(),
+            message,
+        };
+        let input = input
+            .as_json_payload()
+            .expect("query input must be JSON-serializable");
+        let queries = HashMap::from([(
+            QUERY_ID.to_string(),
+            WorkflowQuery {
+                query_type: name.to_string(),
+                query_args: Some(Payloads {
+                    payloads: vec![input],
+                }),
+                header: None,
+            },
+        )]);
+        let task = execution
+            .history
+            .query_response(at, queries)
+            .ok_or_else(|| failed("no workflow task had run by then".to_string()))?;
+
+        let answer = run_query(task, workflow_registrar, self.timeout).await?;
+        match answer.variant {
+            Some(query_result::Variant::Succeeded(success)) => {
+                let payload = success.response.unwrap_or_default();
+                T::from_json_payload(&payload)
+                    .map_err(|e| failed(format!("failed to deserialize result: {e}")))
+            }
+            Some(query_result::Variant::Failed(failure)) => Err(failed(failure.message)),
+            None => Err(failed("the worker gave no answer".to_string())),
+        }
+    }
+
+    /// How the workflow answered an update, or `None` if the update never reached it.
+    pub fn update_outcome(&self, handle: &UpdateHandle) -> Option<&UpdateOutcome> {
+        self.execution
+            .as_ref()
+            .and_then(|execution| execution.updates.get(handle.id()))
+    }
+
+    /// Hand over the outcome of the execution, for a parent running this as its child.
+    pub(crate) fn take_result(&mut self) -> Option<WorkflowTestResult> {
+        self.result.take()
+    }
+
     /// Returns `true` if the workflow has finished executing (success or failure).
     pub fn is_workflow_completed(&self) -> bool {
         self.completed
@@ -145,7 +609,9 @@ impl TestWorkflowEnvironment {
             .as_ref()
             .ok_or(WorkflowResultError::NotExecuted)?;
         match result {
-            Err(failure) => Err(WorkflowResultError::WorkflowFailed(failure.clone())),
+            Err(failure) => Err(WorkflowResultError::WorkflowFailed(Box::new(
+                failure.clone(),
+            ))),
             Ok(None) => Err(WorkflowResultError::NoResult),
             Ok(Some(payloads)) => {
                 let payload = payloads
@@ -157,144 +623,637 @@ impl TestWorkflowEnvironment {
             }
         }
     }
+
+    /// Deserialize a search attribute as last upserted by the workflow.
+    pub fn search_attribute<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.execution
+            .as_ref()?
+            .search_attributes
+            .get(name)
+            .and_then(|payload| T::from_json_payload(payload).ok())
+    }
+
+    /// Deserialize a memo field as last upserted by the workflow.
+    pub fn memo<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.execution
+            .as_ref()?
+            .memo
+            .get(name)
+            .and_then(|payload| T::from_json_payload(payload).ok())
+    }
+
+    /// Every activity the workflow scheduled, in the order it scheduled them.
+    pub fn activity_calls(&self) -> &[ActivityCall] {
+        &self.mocks.calls
+    }
+
+    /// The scheduled calls to one activity type, in order.
+    pub fn activity_calls_to(&self, activity_type: &str) -> Vec<&ActivityCall> {
+        self.mocks
+            .calls
+            .iter()
+            .filter(|call| call.activity_type == activity_type)
+            .collect()
+    }
+
+    /// Panic unless every registered mock was used and every scheduled activity found a
+    /// mock to answer it, like `AssertExpectations` in Go's test suite.
+    pub fn assert_expectations(&self) {
+        let mut problems: Vec<String> = self
+            .mocks
+            .unused
+            .iter()
+            .map(|mock| format!("unused mock: {}", mock.name))
+            .chain(
+                self.mocks
+                    .unused_children
+                    .iter()
+                    .map(|mock| format!("unused child workflow mock: {}", mock.workflow_type)),
+            )
+            .collect();
+        problems.extend(
+            self.mocks
+                .unexpected
+                .iter()
+                .map(|reason| format!("unexpected call: {reason}")),
+        );
+        assert!(
+            problems.is_empty(),
+            "activity mock expectations were not met:\n  {}",
+            problems.join("\n  ")
+        );
+    }
+}
+
+impl Default for TestWorkflowEnvironment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Fluent handle for mocking a single activity.
 ///
 /// Created by [`TestWorkflowEnvironment::on_activity`]. The borrow is released
-/// when `.returns()` or `.returns_err()` is called (both consume `self`).
+/// when one of the `.returns*` methods is called (all consume `self`).
+///
+/// A call is answered by the first unused mock of its activity whose input expectation
+/// it meets. If every such mock rejects the input, the execution fails with
+/// [`TestHarnessError::InputMismatch`], explaining why the first of them did.
+///
+/// The mock answers every attempt at the call. A failed attempt is retried as the
+/// activity's retry policy says: until an attempt succeeds, the policy's maximum attempts
+/// are used up, or the failure is non-retryable because the activity said so or its error
+/// type is one of the policy's `non_retryable_error_types`. The workflow only sees how
+/// the last attempt went.
 pub struct ActivityMockCall<'a> {
-    activity_mocks: &'a mut Vec<(String, ActivityMock)>,
+    activity_mocks: &'a mut Vec<MockedActivity>,
     name: String,
+    input: Option<InputMatcher>,
+    failing_first: Vec<MockFailure>,
 }
 
 impl ActivityMockCall<'_> {
+    /// Only answer calls whose input serializes to the same JSON as `value`.
+    pub fn with_input(mut self, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).expect("expected input must be JSON-serializable");
+        self.input = Some(InputMatcher::equals(value));
+        self
+    }
+
+    /// Only answer calls whose input deserializes into `T` and satisfies `predicate`.
+    pub fn with_input_matching<T, F>(mut self, predicate: F) -> Self
+    where
+        T: DeserializeOwned,
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.input = Some(InputMatcher::matching(predicate));
+        self
+    }
+
+    /// Fail the next `attempts` attempts at the call with a retryable error, before the
+    /// rest of the mock answers. Can be chained to fail with different messages in turn.
+    ///
+    /// ```ignore
+    /// env.on_activity("Activities::withdraw")
+    ///     .fails_first(2, "bank unavailable")
+    ///     .returns("W1234");
+    /// ```
+    pub fn fails_first(mut self, attempts: u32, message: &str) -> Self {
+        self.failing_first
+            .extend((0..attempts).map(|_| MockFailure::new(message)));
+        self
+    }
+
     /// Mock this activity to succeed with the given JSON-serializable value.
     pub fn returns<T: Serialize>(self, value: T) {
         let payload = value
             .as_json_payload()
             .expect("activity result must be JSON-serializable");
-        self.activity_mocks
-            .push((self.name, ActivityMock::Success(payload)));
+        self.push(MockOutcome::Fixed(ActivityMock::Success(payload)));
     }
 
-    /// Mock this activity to fail with the given error message.
+    /// Mock every attempt at this activity to fail with a retryable error with the given
+    /// message.
     pub fn returns_err(self, message: &str) {
-        self.activity_mocks
-            .push((self.name, ActivityMock::Failure(message.to_string())));
+        self.push(MockOutcome::Fixed(ActivityMock::Failure(MockFailure::new(
+            message,
+        ))));
+    }
+
+    /// Mock every attempt at this activity to fail with an application error of type
+    /// `error_type`, which the retry policy gives up on straight away if it lists the type
+    /// as non-retryable.
+    pub fn returns_err_of_type(self, error_type: &str, message: &str) {
+        self.push(MockOutcome::Fixed(ActivityMock::Failure(
+            MockFailure::of_type(error_type, message),
+        )));
+    }
+
+    /// Mock this activity to fail with an error the activity marks non-retryable, so the
+    /// first attempt that gets to it is the last.
+    pub fn returns_non_retryable_err(self, message: &str) {
+        self.push(MockOutcome::Fixed(ActivityMock::Failure(
+            MockFailure::non_retryable(message),
+        )));
+    }
+
+    /// Mock this activity with a closure that computes each attempt's result from the
+    /// deserialized input. `Ok` completes the activity with the value; `Err` fails the
+    /// attempt with the error's message, to be retried.
+    ///
+    /// ```ignore
+    /// env.on_activity("Activities::withdraw")
+    ///     .returns_with(|input: PaymentDetails| match input.amount > Decimal::from(1000) {
+    ///         true => Err("insufficient funds"),
+    ///         false => Ok(format!("W-{}", input.reference_id)),
+    ///     });
+    /// ```
+    pub fn returns_with<I, O, E, F>(self, respond: F)
+    where
+        I: DeserializeOwned,
+        O: Serialize,
+        E: std::fmt::Display,
+        F: Fn(I) -> Result<O, E> + Send + Sync + 'static,
+    {
+        self.returns_with_attempt(move |input, _attempt| respond(input));
+    }
+
+    /// Like [`returns_with`], but the closure is also given the attempt number, counting
+    /// from 1, as the activity would see it.
+    ///
+    /// ```ignore
+    /// env.on_activity("Activities::deposit")
+    ///     .returns_with_attempt(|input: PaymentDetails, attempt| match attempt {
+    ///         1 => Err("connection reset"),
+    ///         _ => Ok(format!("D-{}", input.reference_id)),
+    ///     });
+    /// ```
+    ///
+    /// [`returns_with`]: ActivityMockCall::returns_with
+    pub fn returns_with_attempt<I, O, E, F>(self, respond: F)
+    where
+        I: DeserializeOwned,
+        O: Serialize,
+        E: std::fmt::Display,
+        F: Fn(I, u32) -> Result<O, E> + Send + Sync + 'static,
+    {
+        self.push(MockOutcome::Computed(Box::new(move |input, attempt| {
+            Ok(match respond(deserialize_input(input)?, attempt) {
+                Ok(value) => ActivityMock::Success(
+                    value
+                        .as_json_payload()
+                        .expect("activity result must be JSON-serializable"),
+                ),
+                Err(e) => ActivityMock::Failure(MockFailure::new(e.to_string())),
+            })
+        })));
+    }
+
+    fn push(self, outcome: MockOutcome) {
+        self.activity_mocks.push(MockedActivity {
+            name: self.name,
+            input: self.input,
+            failing_first: self.failing_first,
+            outcome,
+        });
     }
 }
 
 /// Shared execution logic used by `TestWorkflowEnvironment`.
+///
+/// Runs the workflow on a mock worker whose history is grown task by task from the
+/// commands the workflow issues, starting from `history`, and resolves scheduled
+/// activities from `mocks`.
 async fn execute_internal(
-    workflow_name: &str,
-    workflow_registrar: WorkflowRegistrar,
+    history: SyntheticHistory,
+    workflow_registrar: &WorkflowRegistrar,
     activity_registrar: Option<ActivityRegistrar>,
-    input_payloads: Payloads,
-    activity_mocks: &[(String, ActivityMock)],
+    mocks: &mut Mocks,
+    deliveries: Vec<ScheduledDelivery>,
     timeout: Duration,
+) -> Result<(WorkflowTestResult, Execution), TestHarnessError> {
+    // Workflow task completions and failures are forwarded to the driver below.
+    let (event_tx, event_rx) = unbounded_channel();
+    let mut client = mock_worker_client();
+    let completed_tx = event_tx.clone();
+    client
+        .expect_complete_workflow_task()
+        .returning(move |completion| {
+            let _ = completed_tx.send(WorkerEvent::Completed {
+                commands: completion.commands,
+                messages: completion.messages,
+                sdk_metadata: completion.sdk_metadata,
+            });
+            Ok(Default::default())
+        });
+    client
+        .expect_fail_workflow_task()
+        .returning(move |_task_token, _cause, failure| {
+            let _ = event_tx.send(WorkerEvent::Failed(failure.map(Box::new)));
+            Ok(Default::default())
+        });
+
+    // Poll responses are fed to the worker one workflow task at a time.
+    let (poll_tx, poll_rx) = unbounded_channel();
+    let polls = futures::stream::unfold(poll_rx, |mut rx| async move {
+        rx.recv().await.map(|resp| (resp, rx))
+    });
+
+    // Build mock infrastructure
+    let core_worker = mock_worker(MocksHolder::from_wft_stream(client, polls));
+
+    // Build SDK worker and register workflow + activities
+    let mut worker = SdkWorker::new_from_core(Arc::new(core_worker), DataConverter::default());
+    (workflow_registrar)(&mut worker);
+    if let Some(activity_registrar) = activity_registrar {
+        (activity_registrar)(&mut worker);
+    }
+
+    // Race the worker against the history driver.
+    let mut execution = Execution::new(history);
+    let result = tokio::select! {
+        result = worker.run() => {
+            if let Err(e) = result {
+                return Err(TestHarnessError::WorkerError(format!("{e:#}")));
+            }
+            Err(TestHarnessError::NoResult)
+        }
+        result = drive(&mut execution, poll_tx, event_rx, mocks, deliveries) => result,
+        _ = tokio::time::sleep(timeout) => Err(TestHarnessError::NoResult),
+    }?;
+    Ok((result, execution))
+}
+
+/// Feed workflow tasks to the worker until the workflow completes or fails.
+async fn drive(
+    execution: &mut Execution,
+    poll_tx: UnboundedSender<PollWorkflowTaskQueueResponse>,
+    mut events: UnboundedReceiver<WorkerEvent>,
+    mocks: &mut Mocks,
+    mut deliveries: Vec<ScheduledDelivery>,
 ) -> Result<WorkflowTestResult, TestHarnessError> {
-    // Build synthetic history
-    let (t, has_failure) = build_history(workflow_name, input_payloads, activity_mocks);
-
-    // Shared state for capturing results
-    let captured = Arc::new(Mutex::new(CapturedResult::default()));
-    // Notification to signal when a terminal result has been captured
-    let done = Arc::new(Notify::new());
-
-    // Configure mocks
-    let mut mock_cfg = MockPollCfg::from_hist_builder(t);
-    mock_cfg.using_rust_sdk = true;
-    mock_cfg.make_poll_stream_interminable = true;
-
-    if has_failure {
-        mock_cfg.num_expected_fails = 1;
-
-        // Capture WFT failures (activity error propagated via `?`)
-        let captured_for_fail = Arc::clone(&captured);
-        let done_for_fail = Arc::clone(&done);
-        mock_cfg.expect_fail_wft_matcher =
-            Box::new(move |_task_token, _cause, failure: &Option<Failure>| {
-                let mut cap = captured_for_fail.lock().unwrap();
-                let msg = failure
+    let mut timers: Vec<PendingTimer> = Vec::new();
+    let mut children: Vec<StartedChild> = Vec::new();
+    let mut activities: Vec<PendingActivity> = Vec::new();
+
+    let _ = poll_tx.send(execution.history.poll_response());
+
+    while let Some(event) = events.recv().await {
+        let (commands, messages, sdk_metadata) = match event {
+            WorkerEvent::Completed {
+                commands,
+                messages,
+                sdk_metadata,
+            } => (commands, messages, sdk_metadata),
+            WorkerEvent::Failed(failure) => {
+                let message = failure
                     .as_ref()
                     .map(|f| f.message.clone())
                     .unwrap_or_else(|| "WFT failure (no message)".to_string());
-                cap.wft_failure = Some(WorkflowFailure {
-                    message: msg,
-                    failure: failure.clone(),
+                execution.history.final_checkpoint();
+                return Ok(Err(WorkflowFailure {
+                    message,
+                    failure: failure.map(|f| *f),
                     is_wft_failure: true,
-                });
-                done_for_fail.notify_one();
-                true // accept the failure
-            });
-    }
+                }));
+            }
+        };
+
+        // Rejected updates leave no trace in the history, so no command refers to them.
+        for message in &messages {
+            if message
+                .body
+                .as_ref()
+                .is_some_and(|body| body.type_url.ends_with(".Rejection"))
+            {
+                execution.answer_update(message)?;
+            }
+        }
 
-    // Capture successful completions and explicit failures
-    let captured_for_completion = Arc::clone(&captured);
-    let done_for_completion = Arc::clone(&done);
-    mock_cfg.completion_mock_fn = Some(Box::new(move |completion| {
-        let mut cap = captured_for_completion.lock().unwrap();
-        for cmd in &completion.commands {
-            if let Some(ref attrs) = cmd.attributes {
-                match attrs {
-                    command::Attributes::CompleteWorkflowExecutionCommandAttributes(complete) => {
-                        cap.success = Some(complete.result.clone());
-                        done_for_completion.notify_one();
+        execution.history.complete_workflow_task(sdk_metadata);
+        let mut scheduled = Vec::new();
+        let mut starting = Vec::new();
+        for attrs in commands.into_iter().filter_map(|cmd| cmd.attributes) {
+            let history = &mut execution.history;
+            match attrs {
+                command::Attributes::ScheduleActivityTaskCommandAttributes(attrs) => {
+                    let name = attrs
+                        .activity_type
+                        .as_ref()
+                        .map(|t| t.name.clone())
+                        .unwrap_or_default();
+                    let input = input_value(attrs.input.as_ref().and_then(|i| i.payloads.first()));
+                    mocks.calls.push(ActivityCall::new(&attrs, input.clone()));
+                    scheduled.push((
+                        history.schedule_activity(&attrs),
+                        mocks.calls.len() - 1,
+                        name,
+                        input,
+                        attrs.retry_policy,
+                    ));
+                }
+                command::Attributes::StartChildWorkflowExecutionCommandAttributes(attrs) => {
+                    starting.push((history.initiate_child_workflow(&attrs), attrs));
+                }
+                command::Attributes::StartTimerCommandAttributes(attrs) => {
+                    let timeout = attrs
+                        .start_to_fire_timeout
+                        .and_then(|d| Duration::try_from(d).ok())
+                        .unwrap_or_default();
+                    timers.push(PendingTimer {
+                        started_event_id: history.start_timer(&attrs),
+                        timer_id: attrs.timer_id,
+                        fires_at: history.elapsed() + timeout,
+                    });
+                }
+                command::Attributes::CancelTimerCommandAttributes(attrs) => {
+                    if let Some(index) = timers.iter().position(|t| t.timer_id == attrs.timer_id) {
+                        let timer = timers.remove(index);
+                        history.cancel_timer(timer.started_event_id, &timer.timer_id);
+                    }
+                }
+                command::Attributes::RecordMarkerCommandAttributes(attrs) => {
+                    history.record_marker(&attrs);
+                }
+                command::Attributes::RequestCancelActivityTaskCommandAttributes(attrs) => {
+                    let cancel_requested_event_id = history.request_cancel_activity(&attrs);
+                    // A call waiting to retry is cancelled before its next attempt starts.
+                    if let Some(index) = activities
+                        .iter()
+                        .position(|a| a.scheduled_event_id == attrs.scheduled_event_id)
+                    {
+                        let activity = activities.remove(index);
+                        history.activity_cancelled(
+                            activity.scheduled_event_id,
+                            cancel_requested_event_id,
+                        );
                     }
-                    command::Attributes::FailWorkflowExecutionCommandAttributes(fail) => {
-                        let msg = fail
-                            .failure
-                            .as_ref()
-                            .map(|f| f.message.clone())
-                            .unwrap_or_else(|| "workflow failed (no message)".to_string());
-                        cap.command_failure = Some(WorkflowFailure {
-                            message: msg,
-                            failure: fail.failure.clone(),
-                            is_wft_failure: false,
-                        });
-                        done_for_completion.notify_one();
+                }
+                command::Attributes::UpsertWorkflowSearchAttributesCommandAttributes(attrs) => {
+                    history.upsert_search_attributes(&attrs);
+                    if let Some(attributes) = attrs.search_attributes {
+                        execution
+                            .search_attributes
+                            .extend(attributes.indexed_fields);
                     }
-                    _ => {} // Ignore other commands (ScheduleActivity, etc.)
                 }
+                command::Attributes::ModifyWorkflowPropertiesCommandAttributes(attrs) => {
+                    history.modify_workflow_properties(&attrs);
+                    if let Some(upserted) = attrs.upserted_memo {
+                        execution.memo.extend(upserted.fields);
+                    }
+                }
+                command::Attributes::ProtocolMessageCommandAttributes(attrs) => {
+                    if let Some(message) = messages.iter().find(|m| m.id == attrs.message_id) {
+                        execution.answer_update(message)?;
+                    }
+                }
+                command::Attributes::CompleteWorkflowExecutionCommandAttributes(complete) => {
+                    history.final_checkpoint();
+                    return Ok(Ok(complete.result));
+                }
+                command::Attributes::FailWorkflowExecutionCommandAttributes(fail) => {
+                    let message = fail
+                        .failure
+                        .as_ref()
+                        .map(|f| f.message.clone())
+                        .unwrap_or_else(|| "workflow failed (no message)".to_string());
+                    history.final_checkpoint();
+                    return Ok(Err(WorkflowFailure {
+                        message,
+                        failure: fail.failure,
+                        is_wft_failure: false,
+                    }));
+                }
+                command::Attributes::CancelWorkflowExecutionCommandAttributes(cancel) => {
+                    history.final_checkpoint();
+                    return Ok(Err(WorkflowFailure {
+                        message: "workflow cancelled".to_string(),
+                        failure: Some(Failure {
+                            message: "workflow cancelled".to_string(),
+                            failure_info: Some(FailureInfo::CanceledFailureInfo(
+                                CanceledFailureInfo {
+                                    details: cancel.details,
+                                },
+                            )),
+                            ..Default::default()
+                        }),
+                        is_wft_failure: false,
+                    }));
+                }
+                other => return Err(TestHarnessError::UnsupportedCommand(format!("{other:?}"))),
             }
         }
-        Ok(Default::default())
-    }));
+        let history = &mut execution.history;
+        history.checkpoint();
 
-    // Build mock infrastructure
-    let mh = build_mock_pollers(mock_cfg);
-    let core_worker = mock_worker(mh);
+        // Children start at once; mocked ones finish at once too, while executed ones
+        // finish once their own run time has passed.
+        let started_children = !starting.is_empty();
+        for (initiated_event_id, attrs) in starting {
+            let workflow_type = attrs
+                .workflow_type
+                .as_ref()
+                .map(|t| t.name.clone())
+                .unwrap_or_default();
+            let mock = mocks.take_child(&workflow_type)?;
+            let execution = WorkflowExecution {
+                workflow_id: attrs.workflow_id.clone(),
+                run_id: format!("{}-run", attrs.workflow_id),
+            };
+            let started_event_id =
+                history.child_workflow_started(initiated_event_id, &execution, &attrs);
+            let input = input_value(attrs.input.as_ref().and_then(|i| i.payloads.first()));
+            let (outcome, took) = mock.run(input, history.now()).await?;
+            children.push(StartedChild {
+                initiated_event_id,
+                started_event_id,
+                execution,
+                workflow_type: attrs.workflow_type,
+                outcome,
+                finishes_at: history.elapsed() + took,
+            });
+        }
+        finish_children(history, &mut children, history.elapsed());
 
-    // Build SDK worker and register workflow + activities
+        // Every attempt at a call is settled now, but a retried call only resolves once
+        // its backoff has passed.
+        for (scheduled_event_id, call, name, input, retry_policy) in scheduled {
+            let resolution = mocks.answer(&name, &input, retry_policy.as_ref())?;
+            mocks.calls[call].attempt = resolution.attempt;
+            activities.push(PendingActivity {
+                scheduled_event_id,
+                name,
+                finishes_at: history.elapsed() + resolution.backoff,
+                resolution,
+            });
+        }
+        let resolved =
+            finish_activities(history, &mut activities, history.elapsed(), &mut deliveries);
+
+        if !resolved && !started_children {
+            // Nothing happens until the next timer fires, activity or child finishes or
+            // timed delivery is due, so skip straight to it.
+            let next = timers
+                .iter()
+                .map(|timer| timer.fires_at)
+                .chain(activities.iter().map(|activity| activity.finishes_at))
+                .chain(children.iter().map(|child| child.finishes_at))
+                .chain(deliveries.iter().filter_map(|d| match d.trigger {
+                    Trigger::At(at) => Some(at),
+                    Trigger::AfterActivity(_) => None,
+                }))
+                .min();
+            // Nothing left to wait on, so the workflow can never make progress.
+            let Some(next) = next else {
+                return Err(TestHarnessError::NoResult);
+            };
+            history.advance(next);
+            timers.sort_by_key(|timer| timer.fires_at);
+            let fired = timers
+                .iter()
+                .take_while(|timer| timer.fires_at <= next)
+                .count();
+            for timer in timers.drain(..fired) {
+                history.fire_timer(timer.started_event_id, &timer.timer_id);
+            }
+            finish_activities(history, &mut activities, next, &mut deliveries);
+            finish_children(history, &mut children, next);
+            deliveries = deliver_due(
+                history,
+                deliveries,
+                |trigger| matches!(trigger, Trigger::At(at) if *at <= next),
+            );
+        }
+
+        history.start_workflow_task();
+        let _ = poll_tx.send(history.poll_response());
+    }
+
+    Err(TestHarnessError::NoResult)
+}
+
+/// Replay the workflow on a fresh mock worker with `task`, which carries a single query,
+/// and return the worker's answer to it.
+async fn run_query(
+    task: PollWorkflowTaskQueueResponse,
+    workflow_registrar: &WorkflowRegistrar,
+    timeout: Duration,
+) -> Result<QueryResult, TestHarnessError> {
+    let (answer_tx, mut answer_rx) = unbounded_channel();
+    let mut client = mock_worker_client();
+    let answered_tx = answer_tx.clone();
+    client
+        .expect_complete_workflow_task()
+        .returning(move |completion| {
+            if let Some(answer) = completion.query_responses.into_iter().next() {
+                let _ = answered_tx.send(Ok(answer));
+            }
+            Ok(Default::default())
+        });
+    client
+        .expect_fail_workflow_task()
+        .returning(move |_task_token, _cause, failure| {
+            let message = failure
+                .map(|f| f.message)
+                .unwrap_or_else(|| "WFT failure (no message)".to_string());
+            let _ = answer_tx.send(Err(message));
+            Ok(Default::default())
+        });
+
+    // The worker keeps polling after the query is answered, so the stream never ends.
+    let polls = futures::stream::iter([task]).chain(futures::stream::pending());
+    let core_worker = mock_worker(MocksHolder::from_wft_stream(client, polls));
     let mut worker = SdkWorker::new_from_core(Arc::new(core_worker), DataConverter::default());
     (workflow_registrar)(&mut worker);
-    if let Some(activity_registrar) = activity_registrar {
-        (activity_registrar)(&mut worker);
-    }
 
-    // Race the worker against the done notification.
     tokio::select! {
         result = worker.run() => {
             if let Err(e) = result {
                 return Err(TestHarnessError::WorkerError(format!("{e:#}")));
             }
+            Err(TestHarnessError::NoResult)
         }
-        _ = done.notified() => {}
-        _ = tokio::time::sleep(timeout) => {}
+        answer = answer_rx.recv() => match answer {
+            Some(Ok(answer)) => Ok(answer),
+            Some(Err(message)) => Err(TestHarnessError::WorkerError(format!(
+                "replaying the workflow to query it failed: {message}"
+            ))),
+            None => Err(TestHarnessError::NoResult),
+        },
+        _ = tokio::time::sleep(timeout) => Err(TestHarnessError::NoResult),
     }
+}
 
-    // Extract the captured result
-    let cap = captured.lock().unwrap();
-    if let Some(ref failure) = cap.command_failure {
-        return Ok(Err(failure.clone()));
-    }
-    if let Some(ref failure) = cap.wft_failure {
-        return Ok(Err(failure.clone()));
+/// Resolve every activity call that has finished by `now`, in the order they finish, each
+/// followed by the deliveries waiting on it. Returns whether any did.
+fn finish_activities(
+    history: &mut SyntheticHistory,
+    activities: &mut Vec<PendingActivity>,
+    now: Duration,
+    deliveries: &mut Vec<ScheduledDelivery>,
+) -> bool {
+    activities.sort_by_key(|activity| activity.finishes_at);
+    let finished = activities
+        .iter()
+        .take_while(|activity| activity.finishes_at <= now)
+        .count();
+    for activity in activities.drain(..finished) {
+        history.resolve_activity(activity.scheduled_event_id, &activity.resolution);
+        *deliveries = deliver_due(
+            history,
+            std::mem::take(deliveries),
+            |trigger| matches!(trigger, Trigger::AfterActivity(name) if *name == activity.name),
+        );
     }
-    if let Some(ref payloads) = cap.success {
-        return Ok(Ok(payloads.clone()));
+    finished > 0
+}
+
+/// Record every child that has finished by `now`, in the order they finish.
+fn finish_children(
+    history: &mut SyntheticHistory,
+    children: &mut Vec<StartedChild>,
+    now: Duration,
+) {
+    children.sort_by_key(|child| child.finishes_at);
+    let finished = children
+        .iter()
+        .take_while(|child| child.finishes_at <= now)
+        .count();
+    for child in children.drain(..finished) {
+        history.child_workflow_finished(&child);
     }
+}
 
-    Err(TestHarnessError::NoResult)
+/// Deliver, in order, the deliveries whose trigger is `due`, and return the rest.
+fn deliver_due(
+    history: &mut SyntheticHistory,
+    deliveries: Vec<ScheduledDelivery>,
+    due: impl Fn(&Trigger) -> bool,
+) -> Vec<ScheduledDelivery> {
+    let (due, pending): (Vec<_>, _) = deliveries.into_iter().partition(|d| due(&d.trigger));
+    for scheduled in due {
+        scheduled.delivery.deliver(history);
+    }
+    pending
 }
@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::de::DeserializeOwned;
+use temporalio_common::protos::{
+    coresdk::FromJsonPayloadExt,
+    temporal::api::{
+        common::v1::Payload,
+        failure::v1::Failure,
+        update::v1::{Response, outcome},
+    },
+};
+
+use crate::error::UpdateResultError;
+
+/// Identifies an update sent with [`TestWorkflowEnvironment::update_workflow`] or one of
+/// its variants, to look up its outcome once the workflow has run.
+///
+/// [`TestWorkflowEnvironment::update_workflow`]: crate::TestWorkflowEnvironment::update_workflow
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UpdateHandle {
+    id: String,
+}
+
+impl UpdateHandle {
+    pub(crate) fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            id: format!("update-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+
+    /// The update id the workflow sees.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// How the workflow answered an update.
+#[derive(Debug, Clone)]
+pub enum UpdateOutcome {
+    /// The update's validator turned it down, so its handler never ran.
+    Rejected(Failure),
+    /// The update was accepted, but its handler hadn't returned when the workflow finished.
+    Accepted,
+    /// The update was accepted and its handler returned this result.
+    Completed(Option<Payload>),
+    /// The update was accepted and its handler failed.
+    Failed(Failure),
+}
+
+impl UpdateOutcome {
+    pub(crate) fn from_response(response: Response) -> Self {
+        match response.outcome.and_then(|outcome| outcome.value) {
+            Some(outcome::Value::Success(payloads)) => {
+                Self::Completed(payloads.payloads.into_iter().next())
+            }
+            Some(outcome::Value::Failure(failure)) => Self::Failed(failure),
+            None => Self::Completed(None),
+        }
+    }
+
+    /// `true` unless the update was rejected.
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, Self::Rejected(_))
+    }
+
+    /// Deserialize the result the update handler returned.
+    pub fn result<T: DeserializeOwned>(&self) -> Result<T, UpdateResultError> {
+        match self {
+            Self::Rejected(failure) => Err(UpdateResultError::Rejected(failure.message.clone())),
+            Self::Accepted => Err(UpdateResultError::NotCompleted),
+            Self::Failed(failure) => Err(UpdateResultError::Failed(failure.message.clone())),
+            Self::Completed(None) => Err(UpdateResultError::NoResult),
+            Self::Completed(Some(payload)) => T::from_json_payload(payload)
+                .map_err(|e| UpdateResultError::DeserializeError(format!("{e}"))),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Activities run against the mock bank, outside any workflow.
+
+use std::sync::Arc;
+
+use money_transfer_project_template_rust::{
+    activity::Activities, shared::PaymentDetails, trace_context::Traced,
+};
+use rust_decimal::Decimal;
+use temporalio_common::protos::coresdk::activity_task;
+use temporalio_sdk::activities::{ActivityContext, ActivityError};
+use temporalio_sdk_core::test_help::{MocksHolder, mock_worker, mock_worker_client};
+use tokio_util::sync::CancellationToken;
+
+fn activity_context(activity_type: &str) -> ActivityContext {
+    let worker = mock_worker(MocksHolder::from_wft_stream(
+        mock_worker_client(),
+        futures::stream::empty(),
+    ));
+    let task = activity_task::Start {
+        activity_type: activity_type.to_string(),
+        attempt: 1,
+        ..Default::default()
+    };
+    let (ctx, _input) = ActivityContext::new(
+        Arc::new(worker),
+        CancellationToken::new(),
+        "test-queue".to_string(),
+        Vec::new(),
+        task,
+    );
+    ctx
+}
+
+/// A transfer of 100 from `source_account` to `target_account`.
+fn transfer_with(source_account: &str, target_account: &str) -> Traced<PaymentDetails> {
+    let details = PaymentDetails {
+        amount: Decimal::from(100),
+        source_account: source_account.to_string(),
+        target_account: target_account.to_string(),
+        reference_id: "activity-test".to_string(),
+    };
+    Traced::new(details, &Default::default())
+}
+
+#[tokio::test]
+async fn test_deposit_credits_the_target_account() {
+    let deposit = Activities::deposit
+        .run(
+            activity_context("Activities::deposit"),
+            transfer_with("85-150", "99-999"),
+        )
+        .await;
+
+    let error = format!(
+        "{:?}",
+        deposit.expect_err("the target account doesn't exist")
+    );
+    assert!(error.contains("99-999"), "{error}");
+}
+
+#[tokio::test]
+async fn test_refund_credits_the_source_account() {
+    let refund = Activities::refund
+        .run(
+            activity_context("Activities::refund"),
+            transfer_with("99-999", "43-812"),
+        )
+        .await;
+
+    let error = format!(
+        "{:?}",
+        refund.expect_err("the source account doesn't exist")
+    );
+    assert!(error.contains("99-999"), "{error}");
+}
+
+#[tokio::test]
+async fn test_withdrawing_more_than_the_balance_is_not_retried() {
+    let withdraw = Activities::withdraw
+        .run(
+            activity_context("Activities::withdraw"),
+            transfer_with("43-812", "85-150"),
+        )
+        .await;
+
+    match withdraw {
+        Err(ActivityError::NonRetryable(error)) => {
+            assert!(error.to_string().contains("insufficient funds"), "{error}")
+        }
+        other => panic!("expected a non-retryable failure, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_depositing_into_an_unknown_account_is_not_retried() {
+    let deposit = Activities::deposit
+        .run(
+            activity_context("Activities::deposit"),
+            transfer_with("85-150", "99-999"),
+        )
+        .await;
+
+    assert!(
+        matches!(deposit, Err(ActivityError::NonRetryable(_))),
+        "expected a non-retryable failure, got {deposit:?}"
+    );
+}
@@ -0,0 +1,164 @@
+use money_transfer_project_template_rust::bulk::{
+    ImportFormat, RowResult, RowStatus, Summary, default_results_path, parse_transfers,
+    write_results,
+};
+use rust_decimal::Decimal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(
+        ImportFormat::from_path(Path::new("batch.csv")).unwrap(),
+        ImportFormat::Csv
+    );
+    assert_eq!(
+        ImportFormat::from_path(Path::new("batch.jsonl")).unwrap(),
+        ImportFormat::JsonLines
+    );
+    assert_eq!(
+        ImportFormat::from_path(Path::new("batch.ndjson")).unwrap(),
+        ImportFormat::JsonLines
+    );
+    assert!(ImportFormat::from_path(Path::new("batch.txt")).is_err());
+    assert!(ImportFormat::from_path(Path::new("batch")).is_err());
+}
+
+#[test]
+fn test_json_extension_is_rejected_not_read_as_json_lines() {
+    let err = ImportFormat::from_path(Path::new("batch.json")).unwrap_err();
+
+    assert!(err.to_string().contains("expected `csv` or `jsonl`"));
+}
+
+#[test]
+fn test_csv_rows_are_validated_with_line_numbers() {
+    let contents = "\
+amount,source_account,target_account,reference_id
+4.00,85-150,43-812,inv-1
+-1,85-150,43-812,inv-2
+not-a-number,85-150,43-812,inv-3
+5.00, 85-150 ,43-812,inv-1
+2.50,85-150,43-812,inv-4
+";
+
+    let (rows, errors) = parse_transfers(contents, ImportFormat::Csv);
+
+    assert_eq!(rows.iter().map(|r| r.line).collect::<Vec<_>>(), vec![2, 6]);
+    assert_eq!(rows[0].details.amount, Decimal::new(400, 2));
+    assert_eq!(rows[1].details.workflow_id(), "transfer-inv-4");
+    assert_eq!(
+        errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+        vec![3, 4, 5]
+    );
+    assert!(errors[0].message.contains("must be positive"));
+    assert!(errors[2].message.contains("duplicate reference id"));
+}
+
+#[test]
+fn test_json_lines_skip_blank_lines() {
+    let contents = r#"{"amount":"4.00","source_account":"85-150","target_account":"43-812","reference_id":"inv-1"}
+
+{"amount":"4.00","source_account":"85-150","target_account":"85-150","reference_id":"inv-2"}
+{"amount":"4.00"}
+"#;
+
+    let (rows, errors) = parse_transfers(contents, ImportFormat::JsonLines);
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].line, 1);
+    assert_eq!(
+        errors.iter().map(|e| e.line).collect::<Vec<_>>(),
+        vec![3, 4]
+    );
+    assert!(errors[0].message.contains("source and target"));
+    assert!(errors[1].message.contains("missing field"));
+}
+
+fn results_of(statuses: &[RowStatus]) -> Vec<RowResult> {
+    let contents = "amount,source_account,target_account,reference_id\n4.00,85-150,43-812,inv-1\n";
+    let (rows, _) = parse_transfers(contents, ImportFormat::Csv);
+    statuses
+        .iter()
+        .map(|&status| RowResult {
+            status,
+            ..RowResult::started(&rows[0])
+        })
+        .collect()
+}
+
+#[test]
+fn test_summary_counts_each_status_and_picks_the_exit_code() {
+    use RowStatus::*;
+    for (statuses, code) in [
+        (&[Started, AlreadyStarted][..], 0),
+        (&[], 0),
+        (&[Started, Invalid], 2),
+        (&[Started, Failed], 1),
+        (&[Invalid, Failed, AlreadyStarted], 1),
+    ] {
+        let summary = Summary::tally(&results_of(statuses), PathBuf::from("out.jsonl"));
+        assert_eq!(summary.exit_code(), ExitCode::from(code), "{statuses:?}");
+    }
+
+    let summary = Summary::tally(
+        &results_of(&[Started, Started, AlreadyStarted, Invalid, Failed]),
+        PathBuf::from("out.jsonl"),
+    );
+    assert_eq!(
+        summary.to_string(),
+        "2 started, 1 already started, 1 invalid, 1 failed; results written to out.jsonl"
+    );
+}
+
+#[test]
+fn test_results_are_written_one_json_line_per_row() {
+    let contents = "\
+amount,source_account,target_account,reference_id
+4.00,85-150,43-812,inv-1
+-1,85-150,43-812,inv-2
+";
+    let (rows, errors) = parse_transfers(contents, ImportFormat::Csv);
+    let mut started = RowResult::started(&rows[0]);
+    started.run_id = Some("run-1".to_string());
+    let results = vec![
+        started,
+        RowResult::invalid(errors.into_iter().next().unwrap()),
+    ];
+
+    let mut out = Vec::new();
+    write_results(&results, &mut out).unwrap();
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        lines[0],
+        serde_json::json!({
+            "line": 2,
+            "status": "started",
+            "reference_id": "inv-1",
+            "workflow_id": "transfer-inv-1",
+            "run_id": "run-1",
+        })
+    );
+    assert_eq!(lines[1]["line"], 3);
+    assert_eq!(lines[1]["status"], "invalid");
+    assert!(
+        lines[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("must be positive")
+    );
+    assert!(lines[1].get("workflow_id").is_none());
+}
+
+#[test]
+fn test_results_default_to_a_file_next_to_the_import() {
+    assert_eq!(
+        default_results_path(Path::new("batches/march.csv")),
+        PathBuf::from("batches/march.csv.results.jsonl")
+    );
+}
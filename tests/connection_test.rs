@@ -0,0 +1,173 @@
+use assert_matches::assert_matches;
+use clap::Parser;
+use money_transfer_project_template_rust::connection::{
+    ConnectionArgs, ConnectionConfig, ConnectionConfigError, TlsConfig,
+};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+struct TestCli {
+    #[command(flatten)]
+    connection: ConnectionArgs,
+}
+
+/// Write `contents` to a file unique to this test in the temp directory.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_defaults_connect_in_plaintext() {
+    let options = ConnectionConfig::default()
+        .connection_options("test")
+        .unwrap();
+
+    assert_eq!(options.target.as_str(), "http://localhost:7233/");
+    assert!(options.tls_options.is_none());
+    assert!(options.api_key.is_none());
+    assert_eq!(ConnectionConfig::default().namespace(), "default");
+}
+
+#[test]
+fn test_config_file_with_mtls() {
+    let ca = temp_file("ca.pem", "CA");
+    let cert = temp_file("client.pem", "CERT");
+    let key = temp_file("client.key", "KEY");
+    let config = temp_file(
+        "mtls.toml",
+        &format!(
+            r#"
+address = "http://temporal.internal:7233"
+namespace = "payments"
+
+[tls]
+ca_cert = {ca:?}
+client_cert = {cert:?}
+client_key = {key:?}
+server_name = "payments.internal"
+"#
+        ),
+    );
+
+    let config = ConnectionConfig::from_file(&config).unwrap();
+    assert_eq!(config.namespace(), "payments");
+    let options = config.connection_options("test").unwrap();
+
+    assert_eq!(options.target.as_str(), "https://temporal.internal:7233/");
+    let tls = options.tls_options.expect("TLS should be on");
+    assert_eq!(tls.server_root_ca_cert.as_deref(), Some(b"CA".as_slice()));
+    assert_eq!(tls.domain.as_deref(), Some("payments.internal"));
+    let client = tls.client_tls_options.expect("mTLS should be on");
+    assert_eq!(client.client_cert, b"CERT");
+    assert_eq!(client.client_private_key, b"KEY");
+}
+
+#[test]
+fn test_config_file_rejects_unknown_fields() {
+    let config = temp_file("typo.toml", "adress = \"http://localhost:7233\"\n");
+
+    assert_matches!(
+        ConnectionConfig::from_file(&config),
+        Err(ConnectionConfigError::Parse { .. })
+    );
+}
+
+#[test]
+fn test_api_key_turns_tls_on_unless_disabled() {
+    let mut config = ConnectionConfig {
+        api_key: Some("secret".to_string()),
+        ..Default::default()
+    };
+    let options = config.connection_options("test").unwrap();
+    assert!(options.tls_options.is_some());
+    assert_eq!(options.api_key.as_deref(), Some("secret"));
+
+    config.tls.enabled = Some(false);
+    let options = config.connection_options("test").unwrap();
+    assert!(options.tls_options.is_none());
+    assert_eq!(options.target.scheme(), "http");
+}
+
+#[test]
+fn test_client_cert_needs_a_key() {
+    let config = ConnectionConfig {
+        tls: TlsConfig {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    assert_matches!(
+        config.connection_options("test"),
+        Err(ConnectionConfigError::IncompleteClientIdentity)
+    );
+}
+
+#[test]
+fn test_missing_certificate_is_reported_with_its_path() {
+    let config = ConnectionConfig {
+        tls: TlsConfig {
+            ca_cert: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let error = config.connection_options("test").unwrap_err();
+    assert_matches!(
+        &error,
+        ConnectionConfigError::Read { path, .. } if path == Path::new("/nonexistent/ca.pem")
+    );
+}
+
+#[test]
+fn test_flags_override_the_config_file() {
+    let config = temp_file(
+        "override.toml",
+        "address = \"http://file:7233\"\nnamespace = \"from-file\"\n",
+    );
+    let cli = TestCli::parse_from([
+        "test",
+        "--connection-config",
+        config.to_str().unwrap(),
+        "--namespace",
+        "from-flag",
+        "--tls-server-name",
+        "payments.internal",
+    ]);
+
+    let config = cli.connection.config().unwrap();
+    assert_eq!(config.address.as_deref(), Some("http://file:7233"));
+    assert_eq!(config.namespace(), "from-flag");
+    assert_eq!(config.tls.server_name.as_deref(), Some("payments.internal"));
+    assert!(config.tls_enabled());
+}
+
+#[test]
+fn test_debug_output_redacts_the_api_key() {
+    let config = ConnectionConfig {
+        api_key: Some("secret-api-key".to_string()),
+        ..Default::default()
+    };
+    let debug = format!("{config:?}");
+    assert!(!debug.contains("secret-api-key"), "{debug}");
+    assert!(debug.contains("<redacted>"), "{debug}");
+
+    let cli = TestCli::parse_from(["test", "--api-key", "secret-api-key"]);
+    let debug = format!("{:?}", cli.connection);
+    assert!(!debug.contains("secret-api-key"), "{debug}");
+}
+
+#[test]
+fn test_config_file_that_is_not_utf8_is_rejected() {
+    let path = std::env::temp_dir().join(format!("{}-latin1.toml", std::process::id()));
+    std::fs::write(&path, b"namespace = \"caf\xe9\"\n").unwrap();
+
+    assert_matches!(
+        ConnectionConfig::from_file(&path),
+        Err(ConnectionConfigError::Read { .. })
+    );
+}
@@ -0,0 +1,115 @@
+use money_transfer_project_template_rust::fees::{
+    AccountType, EUR_FEE_ACCOUNT, FEE_ACCOUNT, FeeEngine, FeeSchedule, FeeTier, RoundingRule,
+};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str(s).unwrap()
+}
+
+#[test]
+fn test_flat_fee_ignores_amount() {
+    let schedule = FeeSchedule::Flat {
+        amount: dec("2.50"),
+    };
+
+    assert_eq!(schedule.unrounded_fee(dec("1")), dec("2.50"));
+    assert_eq!(schedule.unrounded_fee(dec("1000000")), dec("2.50"));
+}
+
+#[test]
+fn test_percentage_fee_is_clamped() {
+    let schedule = FeeSchedule::Percentage {
+        rate: dec("0.0025"),
+        minimum: Some(dec("1")),
+        maximum: Some(dec("25")),
+    };
+
+    assert_eq!(schedule.unrounded_fee(dec("100")), dec("1"));
+    assert_eq!(schedule.unrounded_fee(dec("2000")), dec("5"));
+    assert_eq!(schedule.unrounded_fee(dec("50000")), dec("25"));
+}
+
+#[test]
+fn test_tiered_fee_charges_each_tier_on_its_part_of_the_amount() {
+    let schedule = FeeSchedule::Tiered {
+        base: dec("0.50"),
+        tiers: vec![
+            FeeTier {
+                up_to: Some(dec("100")),
+                rate: Decimal::ZERO,
+            },
+            FeeTier {
+                up_to: Some(dec("1000")),
+                rate: dec("0.01"),
+            },
+            FeeTier {
+                up_to: None,
+                rate: dec("0.005"),
+            },
+        ],
+    };
+
+    assert_eq!(schedule.unrounded_fee(dec("50")), dec("0.50"));
+    assert_eq!(schedule.unrounded_fee(dec("400")), dec("3.50"));
+    assert_eq!(schedule.unrounded_fee(dec("3000")), dec("19.50"));
+}
+
+#[test]
+fn test_standard_tiered_fee_is_continuous_at_tier_boundaries() {
+    let engine = FeeEngine::standard();
+    let fee = |amount| {
+        engine
+            .quote(AccountType::Personal, "USD", dec(amount))
+            .unwrap()
+            .unrounded_fee
+    };
+
+    assert_eq!(fee("100"), dec("0.50"));
+    assert_eq!(fee("100.01"), dec("0.5001"));
+    assert_eq!(fee("10000"), dec("99.50"));
+    assert_eq!(fee("10000.01"), dec("99.50005"));
+}
+
+#[test]
+fn test_rounding_rules() {
+    assert_eq!(RoundingRule::HalfUp.apply(dec("1.005"), 2), dec("1.01"));
+    assert_eq!(RoundingRule::HalfEven.apply(dec("1.005"), 2), dec("1.00"));
+    assert_eq!(RoundingRule::HalfEven.apply(dec("1.015"), 2), dec("1.02"));
+    assert_eq!(RoundingRule::Up.apply(dec("1.001"), 2), dec("1.01"));
+    assert_eq!(RoundingRule::Down.apply(dec("1.009"), 2), dec("1.00"));
+}
+
+#[test]
+fn test_standard_schedule_is_chosen_by_account_type_and_currency() {
+    let engine = FeeEngine::standard();
+
+    let personal = engine
+        .quote(AccountType::Personal, "USD", dec("123.45"))
+        .unwrap();
+    assert_eq!(personal.unrounded_fee, dec("0.7345"));
+    assert_eq!(personal.fee, dec("0.73"));
+    assert_eq!(personal.fee_account, FEE_ACCOUNT);
+
+    let business = engine
+        .quote(AccountType::Business, "USD", dec("123.45"))
+        .unwrap();
+    assert_eq!(business.fee, dec("1"));
+
+    let euro = engine
+        .quote(AccountType::Business, "EUR", dec("123.45"))
+        .unwrap();
+    assert_eq!(euro.fee, dec("2"));
+    assert_eq!(euro.fee_account, EUR_FEE_ACCOUNT);
+}
+
+#[test]
+fn test_missing_schedule_is_an_error() {
+    let err = FeeEngine::standard()
+        .quote(AccountType::Personal, "JPY", dec("100"))
+        .unwrap_err();
+
+    assert_eq!(err.currency, "JPY");
+    assert_eq!(err.account_type, AccountType::Personal);
+}
@@ -0,0 +1,444 @@
+//! Harness features exercised through small purpose-built workflows.
+
+use std::pin::pin;
+use std::time::{Duration, SystemTime};
+
+use futures::future::{Either, select};
+use temporal_test_harness::{TestHarnessError, TestWorkflowEnvironment, UpdateOutcome};
+use temporalio_common::protos::coresdk::{
+    AsJsonPayloadExt, FromJsonPayloadExt,
+    child_workflow::{Success, child_workflow_result::Status},
+};
+use temporalio_macros::{activities, activity, workflow, workflow_methods};
+use temporalio_sdk::{
+    ActivityOptions, ChildWorkflowOptions, SyncWorkflowContext, WorkflowContext,
+    WorkflowContextView, WorkflowResult, WorkflowTermination,
+    activities::{ActivityContext, ActivityError},
+};
+
+/// Sleeps for the given number of seconds, twice, and reports how long the workflow
+/// clock says each sleep took.
+#[workflow]
+#[derive(Default)]
+pub struct NapWorkflow;
+
+#[workflow_methods]
+impl NapWorkflow {
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>, seconds: u64) -> WorkflowResult<Vec<u64>> {
+        let mut naps = Vec::new();
+        for _ in 0..2 {
+            let before = ctx.workflow_time().unwrap();
+            ctx.timer(Duration::from_secs(seconds)).await;
+            let after = ctx.workflow_time().unwrap();
+            naps.push(after.duration_since(before).unwrap().as_secs());
+        }
+        Ok(naps)
+    }
+}
+
+/// Waits up to an hour for a `confirm` signal.
+#[workflow]
+#[derive(Default)]
+pub struct DeadlineWorkflow {
+    confirmed_by: Option<String>,
+}
+
+#[workflow_methods]
+impl DeadlineWorkflow {
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>, _input: ()) -> WorkflowResult<String> {
+        let confirmed = pin!(ctx.wait_condition(|s| s.confirmed_by.is_some()));
+        let deadline = pin!(ctx.timer(Duration::from_secs(3600)));
+        Ok(match select(confirmed, deadline).await {
+            Either::Left(_) => format!(
+                "confirmed by {}",
+                ctx.state(|s| s.confirmed_by.clone()).unwrap()
+            ),
+            Either::Right(_) => "expired".to_string(),
+        })
+    }
+
+    #[signal]
+    pub fn confirm(&mut self, _ctx: &mut SyncWorkflowContext<Self>, by: String) {
+        self.confirmed_by.get_or_insert(by);
+    }
+}
+
+/// Naps for a minute on either side of a patch, and reports whether the run took the
+/// patched path.
+#[workflow]
+#[derive(Default)]
+pub struct PatchedNapWorkflow;
+
+#[workflow_methods]
+impl PatchedNapWorkflow {
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>, _input: ()) -> WorkflowResult<bool> {
+        ctx.timer(Duration::from_secs(60)).await;
+        let patched = ctx.patched("longer-naps");
+        ctx.timer(Duration::from_secs(60)).await;
+        Ok(patched)
+    }
+}
+
+/// Runs a `NapWorkflow` child for each number of seconds in turn, and reports how long
+/// they napped altogether or which one failed.
+#[workflow]
+#[derive(Default)]
+pub struct NapsWorkflow;
+
+#[workflow_methods]
+impl NapsWorkflow {
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>, naps: Vec<u64>) -> WorkflowResult<String> {
+        let mut total = 0;
+        for (i, seconds) in naps.into_iter().enumerate() {
+            let child = ctx
+                .child_workflow(ChildWorkflowOptions {
+                    workflow_id: format!("nap-{i}"),
+                    workflow_type: NapWorkflow::name().to_string(),
+                    input: vec![seconds.as_json_payload()?],
+                    ..Default::default()
+                })
+                .start()
+                .await
+                .into_started()
+                .expect("child should start");
+            match child.result().await.status {
+                Some(Status::Completed(Success { result })) => {
+                    let naps = Vec::<u64>::from_json_payload(&result.unwrap_or_default())
+                        .map_err(WorkflowTermination::failed)?;
+                    total += naps.iter().sum::<u64>();
+                }
+                Some(Status::Failed(failed)) => {
+                    // The child's own failure is the cause of a `ChildWorkflowFailure`.
+                    let message = failed
+                        .failure
+                        .and_then(|f| f.cause)
+                        .map(|cause| cause.message)
+                        .unwrap_or_default();
+                    return Ok(format!("nap-{i} failed: {message}"));
+                }
+                other => return Ok(format!("nap-{i} ended with {other:?}")),
+            }
+        }
+        Ok(format!("napped {total}s"))
+    }
+}
+
+#[activity]
+#[derive(Default)]
+pub struct Errands;
+
+#[activities]
+impl Errands {
+    #[activity]
+    pub async fn pick_up(_ctx: ActivityContext, round: u32) -> Result<String, ActivityError> {
+        Ok(format!("picked up {round}"))
+    }
+
+    #[activity]
+    pub async fn drop_off(_ctx: ActivityContext, round: u32) -> Result<String, ActivityError> {
+        Ok(format!("dropped off {round}"))
+    }
+}
+
+/// Runs the given number of rounds of `pick_up` then `drop_off`, and reports what each
+/// call returned, in order.
+#[workflow]
+#[derive(Default)]
+pub struct RoundsWorkflow;
+
+#[workflow_methods]
+impl RoundsWorkflow {
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>, rounds: u32) -> WorkflowResult<String> {
+        let options = || ActivityOptions {
+            start_to_close_timeout: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let mut results = Vec::new();
+        for round in 1..=rounds {
+            results.push(
+                ctx.start_activity(Errands::pick_up, round, options())
+                    .await?,
+            );
+            results.push(
+                ctx.start_activity(Errands::drop_off, round, options())
+                    .await?,
+            );
+        }
+        Ok(results.join(", "))
+    }
+}
+
+/// Adds up the amounts sent with `add` updates for an hour, refusing to go past 100.
+#[workflow]
+#[derive(Default)]
+pub struct TallyWorkflow {
+    total: u32,
+}
+
+#[workflow_methods]
+impl TallyWorkflow {
+    #[run]
+    pub async fn run(ctx: &mut WorkflowContext<Self>, _input: ()) -> WorkflowResult<u32> {
+        ctx.timer(Duration::from_secs(3600)).await;
+        Ok(ctx.state(|s| s.total))
+    }
+
+    #[update]
+    pub fn add(
+        &mut self,
+        _ctx: &mut SyncWorkflowContext<Self>,
+        amount: u32,
+    ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        if self.total + amount > 100 {
+            return Err(format!("{amount} would take the total over 100").into());
+        }
+        self.total += amount;
+        Ok(self.total)
+    }
+
+    #[update_validator(add)]
+    pub fn validate_add(
+        &self,
+        _ctx: &WorkflowContextView,
+        amount: &u32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match amount {
+            0 => Err("nothing to add".into()),
+            _ => Ok(()),
+        }
+    }
+
+    #[query]
+    pub fn total(&self, _ctx: &WorkflowContextView) -> u32 {
+        self.total
+    }
+}
+
+#[tokio::test]
+async fn test_timers_skip_ahead_on_the_virtual_clock() {
+    let mut env = TestWorkflowEnvironment::new();
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    env.set_start_time(start);
+    assert_eq!(env.now(), start);
+
+    env.execute_workflow::<NapWorkflow>(86_400)
+        .await
+        .expect("harness should not error");
+
+    let naps: Vec<u64> = env.workflow_result().unwrap();
+    assert_eq!(naps, [86_400, 86_400]);
+    assert_eq!(env.now(), start + Duration::from_secs(2 * 86_400));
+}
+
+#[tokio::test]
+async fn test_patches_survive_the_replay_of_later_tasks() {
+    let mut env = TestWorkflowEnvironment::new();
+
+    env.execute_workflow::<PatchedNapWorkflow>(())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.workflow_error().is_none(), "{:?}", env.workflow_error());
+    let patched: bool = env.workflow_result().unwrap();
+    assert!(patched);
+}
+
+#[tokio::test]
+async fn test_delayed_callback_signals_before_the_deadline() {
+    let mut env = TestWorkflowEnvironment::new();
+    let start = SystemTime::UNIX_EPOCH;
+    env.set_start_time(start);
+    env.register_delayed_callback(Duration::from_secs(1800), move |ctx| {
+        assert_eq!(ctx.now(), start + Duration::from_secs(1800));
+        ctx.signal_workflow("confirm", "alice");
+    });
+
+    env.execute_workflow::<DeadlineWorkflow>(())
+        .await
+        .expect("harness should not error");
+
+    let result: String = env.workflow_result().unwrap();
+    assert_eq!(result, "confirmed by alice");
+    assert_eq!(env.now(), start + Duration::from_secs(1800));
+}
+
+#[tokio::test]
+async fn test_delayed_callback_after_the_deadline_is_too_late() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_delayed_callback(Duration::from_secs(7200), |ctx| {
+        ctx.signal_workflow("confirm", "bob");
+    });
+    let start = env.now();
+
+    env.execute_workflow::<DeadlineWorkflow>(())
+        .await
+        .expect("harness should not error");
+
+    let result: String = env.workflow_result().unwrap();
+    assert_eq!(result, "expired");
+    assert_eq!(env.now(), start + Duration::from_secs(3600));
+}
+
+#[tokio::test]
+async fn test_updates_are_accepted_rejected_or_failed() {
+    let mut env = TestWorkflowEnvironment::new();
+    let added = env.update_workflow_at(Duration::from_secs(600), "add", 5);
+    let empty = env.update_workflow_at(Duration::from_secs(1200), "add", 0);
+    let too_much = env.update_workflow_at(Duration::from_secs(1800), "add", 200);
+
+    env.execute_workflow::<TallyWorkflow>(())
+        .await
+        .expect("harness should not error");
+
+    let added = env.update_outcome(&added).unwrap();
+    assert!(added.is_accepted());
+    assert_eq!(added.result::<u32>().unwrap(), 5);
+
+    let empty = env.update_outcome(&empty).unwrap();
+    assert!(!empty.is_accepted());
+    assert!(
+        matches!(empty, UpdateOutcome::Rejected(failure) if failure.message.contains("nothing to add")),
+        "{empty:?}"
+    );
+
+    let too_much = env.update_outcome(&too_much).unwrap();
+    assert!(too_much.is_accepted());
+    let error = too_much.result::<u32>().unwrap_err();
+    assert!(error.to_string().contains("over 100"), "{error}");
+
+    let total: u32 = env.workflow_result().unwrap();
+    assert_eq!(total, 5);
+}
+
+#[tokio::test]
+async fn test_queries_see_the_state_at_any_point() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.update_workflow_at(Duration::from_secs(600), "add", 5);
+    env.register_delayed_callback(Duration::from_secs(1200), |ctx| {
+        ctx.update_workflow("add", 7);
+    });
+
+    env.execute_workflow::<TallyWorkflow>(())
+        .await
+        .expect("harness should not error");
+
+    let at = |secs| Duration::from_secs(secs);
+    assert_eq!(
+        env.query_workflow_at::<u32>(at(0), "total", ())
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        env.query_workflow_at::<u32>(at(900), "total", ())
+            .await
+            .unwrap(),
+        5
+    );
+    assert_eq!(env.query_workflow::<u32>("total", ()).await.unwrap(), 12);
+
+    let error = env.query_workflow::<u32>("average", ()).await.unwrap_err();
+    assert!(
+        matches!(&error, TestHarnessError::QueryFailed { query, .. } if query == "average"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn test_query_before_execution_is_an_error() {
+    let env = TestWorkflowEnvironment::new();
+    let error = env.query_workflow::<u32>("total", ()).await.unwrap_err();
+    assert!(matches!(error, TestHarnessError::NotExecuted), "{error}");
+}
+
+#[tokio::test]
+async fn test_activity_mocks_are_matched_by_name_then_registration_order() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Errands);
+    // The drop-offs are registered before the pick-ups they follow: only the order
+    // among one activity's mocks decides which call each answers.
+    for round in 1..=3 {
+        env.on_activity("Errands::drop_off")
+            .returns(format!("D{round}"));
+    }
+    for round in 1..=3 {
+        env.on_activity("Errands::pick_up")
+            .returns(format!("P{round}"));
+    }
+
+    env.execute_workflow::<RoundsWorkflow>(3)
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let result: String = env.workflow_result().unwrap();
+    assert_eq!(result, "P1, D1, P2, D2, P3, D3");
+}
+
+#[tokio::test]
+async fn test_mocked_child_workflows_answer_in_order() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.on_child_workflow::<NapWorkflow>().returns([10, 10]);
+    env.on_child_workflow::<NapWorkflow>().returns([5, 5]);
+    let start = env.now();
+
+    env.execute_workflow::<NapsWorkflow>(vec![10, 5])
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let result: String = env.workflow_result().unwrap();
+    assert_eq!(result, "napped 30s");
+    assert_eq!(env.now(), start);
+}
+
+#[tokio::test]
+async fn test_mocked_child_workflow_failure_reaches_the_parent() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.on_child_workflow::<NapWorkflow>().returns([10, 10]);
+    env.on_child_workflow::<NapWorkflow>()
+        .returns_err("too tired");
+
+    env.execute_workflow::<NapsWorkflow>(vec![10, 5, 1])
+        .await
+        .expect("harness should not error");
+
+    let result: String = env.workflow_result().unwrap();
+    assert_eq!(result, "nap-1 failed: too tired");
+}
+
+#[tokio::test]
+async fn test_executed_child_workflows_run_on_the_parents_clock() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.on_child_workflow::<NapWorkflow>().executes(|_| {});
+    env.on_child_workflow::<NapWorkflow>().executes(|_| {});
+    let start = env.now();
+
+    env.execute_workflow::<NapsWorkflow>(vec![3600, 60])
+        .await
+        .expect("harness should not error");
+
+    let result: String = env.workflow_result().unwrap();
+    assert_eq!(result, "napped 7320s");
+    assert_eq!(env.now(), start + Duration::from_secs(7320));
+}
+
+#[tokio::test]
+async fn test_unmocked_child_workflow_is_reported() {
+    let mut env = TestWorkflowEnvironment::new();
+
+    let error = env
+        .execute_workflow::<NapsWorkflow>(vec![10])
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(&error, TestHarnessError::UnmockedChildWorkflow(name) if name == NapWorkflow::name()),
+        "{error}"
+    );
+}
@@ -0,0 +1,67 @@
+use money_transfer_project_template_rust::{
+    banking_client::BankingService, metrics, search_attributes::TransferStatus,
+};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use temporalio_common::telemetry::{
+    TaskQueueLabelStrategy,
+    metrics::{CoreMeter, NewAttributes, TemporalMeter},
+    start_prometheus_metric_exporter,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+async fn scrape(address: std::net::SocketAddr) -> String {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn test_transfer_and_bank_metrics_are_scraped() {
+    let server = start_prometheus_metric_exporter(metrics::prometheus_options(
+        "127.0.0.1:0".parse().unwrap(),
+    ))
+    .unwrap();
+    metrics::install(TemporalMeter::new(
+        server.meter.clone() as Arc<dyn CoreMeter>,
+        NewAttributes::default(),
+        TaskQueueLabelStrategy::UseNormal,
+    ));
+
+    metrics::record_transfer(TransferStatus::Completed, Decimal::from(250));
+    let bank = BankingService {
+        hostname: "bank-api.example.com".to_string(),
+    };
+    bank.withdraw(
+        "85-150".to_string(),
+        Decimal::from(1_000_000),
+        "metrics-test".to_string(),
+    )
+    .unwrap_err();
+
+    let body = scrape(server.bound_addr).await;
+    assert!(
+        body.contains(r#"money_transfer_transfers{outcome="Completed"} 1"#),
+        "{body}"
+    );
+    assert!(
+        body.contains(r#"money_transfer_amount_bucket{outcome="Completed",le="500"} 1"#),
+        "{body}"
+    );
+    assert!(
+        body.contains(r#"money_transfer_bank_call_latency_count{operation="withdraw"} 1"#),
+        "{body}"
+    );
+    assert!(
+        body.contains(
+            r#"money_transfer_bank_call_errors{error="insufficient_funds",operation="withdraw"} 1"#
+        ),
+        "{body}"
+    );
+}
@@ -0,0 +1,248 @@
+use clap::Parser;
+use money_transfer_project_template_rust::{
+    fees::{AccountType, FeeEngine},
+    search_attributes::TransferStatus,
+    shared::TransferResult,
+    starter::{Outcome, Report, TransferArgs},
+};
+use rust_decimal::Decimal;
+use std::process::ExitCode;
+use temporalio_common::protos::temporal::api::enums::v1::{
+    WorkflowIdConflictPolicy, WorkflowIdReusePolicy,
+};
+
+#[derive(Parser)]
+struct TestCli {
+    #[command(flatten)]
+    transfer: TransferArgs,
+}
+
+fn parse(args: &[&str]) -> TransferArgs {
+    TestCli::parse_from(std::iter::once("start").chain(args.iter().copied())).transfer
+}
+
+#[test]
+fn test_transfer_flags_build_the_payment() {
+    let args = parse(&[
+        "--amount",
+        "250.00",
+        "--from",
+        "43-812",
+        "--to",
+        "85-150",
+        "--reference",
+        "inv-9001",
+    ]);
+
+    let payment = args.payment().unwrap();
+    assert_eq!(payment.amount, Decimal::new(25000, 2));
+    assert_eq!(payment.source_account, "43-812");
+    assert_eq!(payment.target_account, "85-150");
+    assert_eq!(payment.reference_id, "inv-9001");
+    assert_eq!(args.workflow_id(&payment), "transfer-inv-9001");
+    assert!(!args.wait);
+    assert_eq!(args.execution_timeout, None);
+}
+
+#[test]
+fn test_unset_transfer_flags_use_the_sample_payment() {
+    let args = parse(&[]);
+
+    let payment = args.payment().unwrap();
+    assert_eq!(payment.amount, Decimal::new(400, 2));
+    assert_eq!(payment.source_account, "85-150");
+    assert_eq!(payment.target_account, "43-812");
+    assert_eq!(args.workflow_id(&payment), "transfer-12345");
+}
+
+#[test]
+fn test_workflow_id_flag_overrides_the_reference() {
+    let args = parse(&["--workflow-id", "payroll-7", "--wait"]);
+
+    let payment = args.payment().unwrap();
+    assert_eq!(args.workflow_id(&payment), "payroll-7");
+    assert!(args.wait);
+}
+
+#[test]
+fn test_invalid_transfer_flags_are_rejected() {
+    for invalid in [
+        &["--amount", "0"][..],
+        &["--amount", "1.001"],
+        &["--from", "43-812"],
+        &["--to", "nowhere"],
+        &["--workflow-id", " "],
+    ] {
+        assert!(parse(invalid).payment().is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn test_amount_that_is_not_a_number_fails_to_parse() {
+    assert!(TestCli::try_parse_from(["start", "--amount", "lots"]).is_err());
+}
+
+#[test]
+fn test_id_policies_default_to_refusing_repeats_and_attaching_to_open_runs() {
+    let args = parse(&[]);
+
+    assert_eq!(
+        args.id_policy.reuse_policy(),
+        WorkflowIdReusePolicy::RejectDuplicate
+    );
+    assert_eq!(
+        args.id_policy.conflict_policy(),
+        WorkflowIdConflictPolicy::UseExisting
+    );
+}
+
+#[test]
+fn test_id_reuse_policy_flags_map_to_the_servers_policies() {
+    for (flag, policy) in [
+        ("allow-duplicate", WorkflowIdReusePolicy::AllowDuplicate),
+        (
+            "allow-duplicate-failed-only",
+            WorkflowIdReusePolicy::AllowDuplicateFailedOnly,
+        ),
+        ("reject-duplicate", WorkflowIdReusePolicy::RejectDuplicate),
+    ] {
+        let args = parse(&["--id-reuse-policy", flag]);
+        assert_eq!(args.id_policy.reuse_policy(), policy, "{flag}");
+    }
+}
+
+#[test]
+fn test_id_conflict_policy_flags_map_to_the_servers_policies() {
+    for (flag, policy) in [
+        ("attach", WorkflowIdConflictPolicy::UseExisting),
+        ("fail", WorkflowIdConflictPolicy::Fail),
+        (
+            "terminate-existing",
+            WorkflowIdConflictPolicy::TerminateExisting,
+        ),
+    ] {
+        let args = parse(&["--id-conflict-policy", flag]);
+        assert_eq!(args.id_policy.conflict_policy(), policy, "{flag}");
+    }
+}
+
+#[test]
+fn test_unknown_id_policy_fails_to_parse() {
+    assert!(TestCli::try_parse_from(["start", "--id-conflict-policy", "ignore"]).is_err());
+}
+
+fn report(outcome: Outcome) -> Report {
+    Report {
+        workflow_id: "transfer-inv-9001".to_string(),
+        run_id: Some("run-1".to_string()),
+        attached: false,
+        outcome,
+        result: None,
+        failure: None,
+    }
+}
+
+#[test]
+fn test_outcomes_map_to_their_exit_codes() {
+    for (outcome, code) in [
+        (Outcome::Started, 0),
+        (Outcome::Completed, 0),
+        (Outcome::Failed, 3),
+        (Outcome::Compensated, 4),
+        (Outcome::Cancelled, 5),
+        (Outcome::TimedOut, 6),
+    ] {
+        assert_eq!(outcome.exit_code(), ExitCode::from(code));
+    }
+}
+
+#[test]
+fn test_failure_is_compensated_only_when_the_workflow_says_so() {
+    assert!(matches!(
+        Outcome::of_failure(Some(TransferStatus::Compensated)),
+        Outcome::Compensated
+    ));
+    assert!(matches!(
+        Outcome::of_failure(Some(TransferStatus::Failed)),
+        Outcome::Failed
+    ));
+    assert!(matches!(Outcome::of_failure(None), Outcome::Failed));
+}
+
+#[test]
+fn test_report_describes_each_outcome() {
+    let failed = |outcome| Report {
+        failure: Some("deposit failed".to_string()),
+        ..report(outcome)
+    };
+    for (report, expected) in [
+        (
+            report(Outcome::Started),
+            "Started workflow transfer-inv-9001, run_id: run-1",
+        ),
+        (
+            Report {
+                attached: true,
+                ..report(Outcome::Started)
+            },
+            "Transfer transfer-inv-9001 was already started, run_id: run-1",
+        ),
+        (
+            report(Outcome::Completed),
+            "Transfer transfer-inv-9001 completed",
+        ),
+        (
+            failed(Outcome::Failed),
+            "Transfer transfer-inv-9001 failed: deposit failed",
+        ),
+        (
+            failed(Outcome::Compensated),
+            "Transfer transfer-inv-9001 failed and was compensated: deposit failed",
+        ),
+        (
+            report(Outcome::Cancelled),
+            "Transfer transfer-inv-9001 was cancelled",
+        ),
+        (
+            report(Outcome::TimedOut),
+            "Transfer transfer-inv-9001 timed out",
+        ),
+    ] {
+        assert_eq!(report.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_report_json_leaves_out_what_is_unknown() {
+    assert_eq!(
+        serde_json::json!(report(Outcome::Started)),
+        serde_json::json!({
+            "workflow_id": "transfer-inv-9001",
+            "run_id": "run-1",
+            "attached": false,
+            "outcome": "started",
+        })
+    );
+
+    let result = TransferResult {
+        withdraw_transaction_id: "W1".to_string(),
+        deposit_transaction_id: "D1".to_string(),
+        fee_transaction_id: None,
+        principal: Decimal::from(4),
+        total_debited: Decimal::from(4),
+        fee: FeeEngine::standard()
+            .quote(AccountType::Personal, "USD", Decimal::from(4))
+            .unwrap(),
+        approved_by: None,
+        late_cancellation: None,
+    };
+    let completed = Report {
+        result: Some(result.clone()),
+        ..report(Outcome::Completed)
+    };
+    let json = serde_json::json!(completed);
+    assert_eq!(json["outcome"], "completed");
+    assert_eq!(json["result"], serde_json::json!(result));
+    assert!(json.get("failure").is_none());
+    assert_eq!(completed.to_string(), result.to_string());
+}
@@ -0,0 +1,195 @@
+use money_transfer_project_template_rust::{
+    search_attributes::{TransferStatus, status_search_attribute},
+    timeline::{Timeline, is_close_event},
+};
+use std::time::{Duration, SystemTime};
+use temporalio_common::protos::{
+    coresdk::AsJsonPayloadExt,
+    temporal::api::{
+        common::v1::{ActivityType, Payloads, SearchAttributes},
+        failure::v1::Failure,
+        history::v1::{
+            ActivityTaskCompletedEventAttributes, ActivityTaskScheduledEventAttributes,
+            ActivityTaskStartedEventAttributes, HistoryEvent,
+            UpsertWorkflowSearchAttributesEventAttributes,
+            WorkflowExecutionCompletedEventAttributes, WorkflowExecutionSignaledEventAttributes,
+            WorkflowTaskCompletedEventAttributes, history_event::Attributes,
+        },
+        workflow::v1::PendingActivityInfo,
+    },
+};
+
+fn event(event_id: i64, attributes: Attributes) -> HistoryEvent {
+    HistoryEvent {
+        event_id,
+        attributes: Some(attributes),
+        ..Default::default()
+    }
+}
+
+/// Run `events` through a fresh timeline and return the messages it produced.
+fn messages(events: &[HistoryEvent]) -> Vec<String> {
+    let mut timeline = Timeline::new();
+    events
+        .iter()
+        .filter_map(|event| timeline.describe(event))
+        .map(|entry| entry.message)
+        .collect()
+}
+
+#[test]
+fn test_activity_retries_are_named_with_their_failure() {
+    let events = [
+        event(
+            5,
+            Attributes::ActivityTaskScheduledEventAttributes(
+                ActivityTaskScheduledEventAttributes {
+                    activity_type: Some(ActivityType {
+                        name: "Activities::withdraw".to_string(),
+                    }),
+                    ..Default::default()
+                },
+            ),
+        ),
+        event(
+            6,
+            Attributes::ActivityTaskStartedEventAttributes(ActivityTaskStartedEventAttributes {
+                scheduled_event_id: 5,
+                attempt: 3,
+                last_failure: Some(Failure {
+                    message: "bank unavailable".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        ),
+        event(
+            7,
+            Attributes::ActivityTaskCompletedEventAttributes(
+                ActivityTaskCompletedEventAttributes {
+                    scheduled_event_id: 5,
+                    started_event_id: 6,
+                    ..Default::default()
+                },
+            ),
+        ),
+    ];
+
+    assert_eq!(
+        messages(&events),
+        vec![
+            "Activities::withdraw scheduled",
+            "Activities::withdraw retried, attempt 3 after: bank unavailable",
+            "Activities::withdraw completed",
+        ]
+    );
+}
+
+#[test]
+fn test_signals_and_status_changes_are_shown() {
+    let (name, status) = status_search_attribute(TransferStatus::AwaitingApproval);
+    let events = [
+        event(
+            8,
+            Attributes::UpsertWorkflowSearchAttributesEventAttributes(
+                UpsertWorkflowSearchAttributesEventAttributes {
+                    search_attributes: Some(SearchAttributes {
+                        indexed_fields: [(name, status)].into(),
+                    }),
+                    ..Default::default()
+                },
+            ),
+        ),
+        event(
+            9,
+            Attributes::WorkflowExecutionSignaledEventAttributes(
+                WorkflowExecutionSignaledEventAttributes {
+                    signal_name: "approve".to_string(),
+                    input: Some(Payloads {
+                        payloads: vec!["alice".as_json_payload().unwrap()],
+                    }),
+                    ..Default::default()
+                },
+            ),
+        ),
+    ];
+
+    assert_eq!(
+        messages(&events),
+        vec![
+            "status AwaitingApproval",
+            "signal approve received: \"alice\""
+        ]
+    );
+}
+
+#[test]
+fn test_workflow_tasks_are_skipped_and_completion_closes() {
+    let task = event(
+        3,
+        Attributes::WorkflowTaskCompletedEventAttributes(
+            WorkflowTaskCompletedEventAttributes::default(),
+        ),
+    );
+    let completed = event(
+        12,
+        Attributes::WorkflowExecutionCompletedEventAttributes(
+            WorkflowExecutionCompletedEventAttributes::default(),
+        ),
+    );
+
+    assert_eq!(
+        messages(&[task.clone(), completed.clone()]),
+        vec!["workflow completed"]
+    );
+    assert!(!is_close_event(&task));
+    assert!(is_close_event(&completed));
+}
+
+#[test]
+fn test_pending_activity_retries_are_shown_once_per_attempt() {
+    let backing_off = PendingActivityInfo {
+        activity_id: "3".to_string(),
+        activity_type: Some(ActivityType {
+            name: "Activities::withdraw".to_string(),
+        }),
+        attempt: 2,
+        last_failure: Some(Failure {
+            message: "bank unavailable".to_string(),
+            ..Default::default()
+        }),
+        next_attempt_schedule_time: Some(
+            (SystemTime::UNIX_EPOCH + Duration::from_secs(1_792_322_726)).into(),
+        ),
+        ..Default::default()
+    };
+    let running = PendingActivityInfo {
+        next_attempt_schedule_time: None,
+        ..backing_off.clone()
+    };
+    let mut timeline = Timeline::new();
+
+    let entry = timeline.describe_pending(&backing_off).unwrap();
+    assert_eq!(entry.attempt, 2);
+    assert_eq!(entry.last_failure.as_deref(), Some("bank unavailable"));
+    assert_eq!(
+        entry.next_attempt_time.as_deref(),
+        Some("2026-10-18T11:25:26Z")
+    );
+    assert!(
+        entry.to_string().ends_with(
+            "Activities::withdraw attempt 2 due at 2026-10-18T11:25:26Z after: bank unavailable"
+        ),
+        "{entry}"
+    );
+
+    // Polling again before anything changed reports nothing new.
+    assert_eq!(timeline.describe_pending(&backing_off), None);
+    let entry = timeline.describe_pending(&running).unwrap();
+    assert!(
+        entry
+            .to_string()
+            .ends_with("Activities::withdraw attempt 2 running after: bank unavailable"),
+        "{entry}"
+    );
+}
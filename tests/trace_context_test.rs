@@ -0,0 +1,165 @@
+use money_transfer_project_template_rust::{
+    activity::Activities,
+    banking_client::BankingService,
+    fees::{AccountType, FeeEngine},
+    shared::PaymentDetails,
+    trace_context::{TRACE_HEADER, TraceContext, Traced, otel_layer},
+    workflow::MoneyTransferWorkflow,
+};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+use rust_decimal::Decimal;
+use temporal_test_harness::TestWorkflowEnvironment;
+use tracing_subscriber::layer::SubscriberExt;
+
+fn test_provider() -> (SdkTracerProvider, InMemorySpanExporter) {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    (provider, exporter)
+}
+
+#[test]
+fn test_context_is_empty_without_an_exporting_subscriber() {
+    let span = tracing::info_span!("start transfer");
+    let context = TraceContext::of(&span);
+    assert!(context.is_empty());
+    assert_eq!(context.traceparent(), None);
+    assert_eq!(context.header(), None);
+}
+
+#[test]
+fn test_header_round_trip() {
+    let (provider, _) = test_provider();
+    let subscriber = tracing_subscriber::registry().with(otel_layer(&provider));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("start transfer");
+        let context = TraceContext::of(&span);
+        assert!(context.traceparent().is_some_and(|t| t.starts_with("00-")));
+
+        let header = context.header().unwrap();
+        assert!(header.fields.contains_key(TRACE_HEADER));
+        assert_eq!(TraceContext::from_headers(&header.fields), context);
+    });
+    assert!(TraceContext::from_headers(&Default::default()).is_empty());
+}
+
+#[test]
+fn test_bank_call_joins_the_starters_trace() {
+    let (provider, exporter) = test_provider();
+    let subscriber = tracing_subscriber::registry().with(otel_layer(&provider));
+    tracing::subscriber::with_default(subscriber, || {
+        // What the starter sends, carried to the activity without any span in between.
+        let context = TraceContext::of(&tracing::info_span!("start transfer"));
+
+        let activity = tracing::info_span!("activity");
+        context.set_as_parent_of(&activity);
+        activity.in_scope(|| {
+            let bank = BankingService {
+                hostname: "bank-api.example.com".to_string(),
+            };
+            bank.deposit(
+                "43-812".to_string(),
+                Decimal::from(4),
+                "trace-test".to_string(),
+            )
+            .unwrap();
+        });
+    });
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let names: Vec<_> = spans.iter().map(|span| span.name.as_ref()).collect();
+    assert!(names.contains(&"start transfer"), "{names:?}");
+    assert!(names.contains(&"activity"), "{names:?}");
+    assert!(names.contains(&"Bank:deposit"), "{names:?}");
+    let trace_id = spans[0].span_context.trace_id();
+    assert!(
+        spans
+            .iter()
+            .all(|span| span.span_context.trace_id() == trace_id)
+    );
+
+    let bank = spans
+        .iter()
+        .find(|span| span.name == "Bank:deposit")
+        .unwrap();
+    let activity = spans.iter().find(|span| span.name == "activity").unwrap();
+    assert_eq!(bank.parent_span_id, activity.span_context.span_id());
+}
+
+#[test]
+fn test_traced_input_inlines_the_payload() {
+    let details = PaymentDetails {
+        amount: Decimal::new(400, 2),
+        source_account: "85-150".to_string(),
+        target_account: "43-812".to_string(),
+        reference_id: "trace-test".to_string(),
+    };
+
+    // Without a trace the activity input is exactly the payment details.
+    let untraced =
+        serde_json::to_value(Traced::new(details.clone(), &TraceContext::default())).unwrap();
+    assert_eq!(untraced, serde_json::to_value(&details).unwrap());
+
+    let (provider, _) = test_provider();
+    let subscriber = tracing_subscriber::registry().with(otel_layer(&provider));
+    tracing::subscriber::with_default(subscriber, || {
+        let context = TraceContext::of(&tracing::info_span!("workflow"));
+        let json = serde_json::to_value(Traced::new(details.clone(), &context)).unwrap();
+        assert_eq!(json["reference_id"], "trace-test");
+        assert!(json["trace_context"]["traceparent"].is_string(), "{json}");
+
+        let traced: Traced<PaymentDetails> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(traced.trace_context, context);
+        // The payment details themselves never carry a trace.
+        let details: PaymentDetails = serde_json::from_value(json).unwrap();
+        assert!(
+            !serde_json::to_value(details)
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .contains_key("trace_context")
+        );
+    });
+}
+
+#[tokio::test]
+async fn test_workflow_run_has_its_own_span() {
+    let (provider, exporter) = test_provider();
+    let subscriber = tracing_subscriber::registry().with(otel_layer(&provider));
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee").returns(
+        FeeEngine::standard()
+            .quote(AccountType::Personal, "USD", Decimal::from(400))
+            .unwrap(),
+    );
+    env.on_activity("Activities::withdraw").returns("W1");
+    env.on_activity("Activities::deposit").returns("D1");
+    env.on_activity("Activities::post_fee").returns("F1");
+    env.execute_workflow::<MoneyTransferWorkflow>(PaymentDetails {
+        amount: Decimal::from(400),
+        source_account: "85-150".to_string(),
+        target_account: "43-812".to_string(),
+        reference_id: "trace-test".to_string(),
+    })
+    .await
+    .expect("harness should not error");
+    assert!(env.workflow_error().is_none());
+
+    // The harness replays the run for each workflow task, but only the first, live run
+    // reports a span.
+    let spans = exporter.get_finished_spans().unwrap();
+    let runs: Vec<_> = spans
+        .iter()
+        .filter(|span| span.name == "RunWorkflow:MoneyTransferWorkflow")
+        .collect();
+    assert_eq!(runs.len(), 1, "{spans:?}");
+    // Without a trace from the starter, activities have none to continue.
+    for call in env.activity_calls() {
+        let input = call.input::<Traced<PaymentDetails>>().unwrap();
+        assert!(input.trace_context.is_empty(), "{}", call.activity_type);
+    }
+}
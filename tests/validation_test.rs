@@ -0,0 +1,61 @@
+use money_transfer_project_template_rust::shared::{InvalidPaymentError, PaymentDetails};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+fn payment(amount: &str, source_account: &str, target_account: &str) -> PaymentDetails {
+    PaymentDetails {
+        amount: Decimal::from_str(amount).unwrap(),
+        source_account: source_account.to_string(),
+        target_account: target_account.to_string(),
+        reference_id: "test-ref-001".to_string(),
+    }
+}
+
+#[test]
+fn test_valid_payment() {
+    assert_eq!(payment("4.00", "85-150", "43-812").validate(), Ok(()));
+    // Trailing zeros beyond two places don't add precision.
+    assert_eq!(payment("4.000", "85-150", "43-812").validate(), Ok(()));
+}
+
+#[test]
+fn test_malformed_account() {
+    for account in ["85150", "8-5150", "85-15", "ab-cde", "85-150-1", ""] {
+        assert_eq!(
+            payment("4.00", account, "43-812").validate(),
+            Err(InvalidPaymentError::MalformedAccount(account.to_string()))
+        );
+    }
+    assert_eq!(
+        payment("4.00", "85-150", "43_812").validate(),
+        Err(InvalidPaymentError::MalformedAccount("43_812".to_string()))
+    );
+}
+
+#[test]
+fn test_non_positive_amount() {
+    assert!(matches!(
+        payment("0", "85-150", "43-812").validate(),
+        Err(InvalidPaymentError::NonPositiveAmount(_))
+    ));
+    assert!(matches!(
+        payment("-4.00", "85-150", "43-812").validate(),
+        Err(InvalidPaymentError::NonPositiveAmount(_))
+    ));
+}
+
+#[test]
+fn test_amount_scale() {
+    assert!(matches!(
+        payment("4.001", "85-150", "43-812").validate(),
+        Err(InvalidPaymentError::AmountScale(_))
+    ));
+}
+
+#[test]
+fn test_same_account() {
+    assert_eq!(
+        payment("4.00", "85-150", "85-150").validate(),
+        Err(InvalidPaymentError::SameAccount("85-150".to_string()))
+    );
+}
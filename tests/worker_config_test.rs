@@ -0,0 +1,185 @@
+use assert_matches::assert_matches;
+use clap::Parser;
+use money_transfer_project_template_rust::{
+    approval::{ApprovalPolicy, DEFAULT_APPROVAL_TIMEOUT},
+    logging::LogFormat,
+    shared::MONEY_TRANSFER_TASK_QUEUE_NAME,
+    worker_config::{WorkerArgs, WorkerConfig, WorkerConfigError},
+};
+use rust_decimal::Decimal;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+struct TestCli {
+    #[command(flatten)]
+    worker: WorkerArgs,
+}
+
+/// Write `contents` to a file unique to this test in the temp directory.
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_unset_fields_use_the_documented_defaults() {
+    let config = WorkerConfig::default();
+
+    assert_eq!(config.task_queue(), MONEY_TRANSFER_TASK_QUEUE_NAME);
+    assert_eq!(config.max_concurrent_workflow_tasks(), 100);
+    assert_eq!(config.max_concurrent_activities(), 100);
+    assert_eq!(config.workflow_pollers(), 5);
+    assert_eq!(config.activity_pollers(), 5);
+    assert_eq!(config.sticky_cache_size(), 1000);
+    assert_eq!(config.shutdown_grace_period(), Duration::from_secs(30));
+    assert!(config.identity.is_none());
+    assert!(config.activities_per_second.is_none());
+    assert_eq!(config.log_format(), LogFormat::Text);
+    assert_eq!(config.log_filter(), "info");
+    config.validate().unwrap();
+}
+
+#[test]
+fn test_config_file_is_read() {
+    let path = temp_file(
+        "worker.toml",
+        r#"
+task_queue = "payments"
+identity = "transfer-worker-1"
+max_concurrent_activities = 20
+activity_pollers = 8
+activities_per_second = 2.5
+log_format = "json"
+"#,
+    );
+
+    let config = WorkerConfig::from_file(&path).unwrap();
+    assert_eq!(config.task_queue(), "payments");
+    assert_eq!(config.identity.as_deref(), Some("transfer-worker-1"));
+    assert_eq!(config.max_concurrent_activities(), 20);
+    assert_eq!(config.activity_pollers(), 8);
+    assert_eq!(config.activities_per_second, Some(2.5));
+    assert_eq!(config.workflow_pollers(), 5);
+    assert_eq!(config.log_format(), LogFormat::Json);
+}
+
+#[test]
+fn test_config_file_rejects_unknown_fields() {
+    let path = temp_file("worker-typo.toml", "max_concurent_activities = 20\n");
+
+    assert_matches!(
+        WorkerConfig::from_file(&path),
+        Err(WorkerConfigError::Parse { .. })
+    );
+}
+
+#[test]
+fn test_flags_override_the_config_file() {
+    let path = temp_file(
+        "worker-override.toml",
+        "task_queue = \"from-file\"\nworkflow_pollers = 3\n",
+    );
+    let cli = TestCli::parse_from([
+        "test",
+        "--worker-config",
+        path.to_str().unwrap(),
+        "--task-queue",
+        "from-flag",
+        "--max-concurrent-workflow-tasks",
+        "10",
+        "--shutdown-grace-period",
+        "5",
+        "--log-format",
+        "pretty",
+    ]);
+
+    let config = cli.worker.config().unwrap();
+    assert_eq!(config.task_queue(), "from-flag");
+    assert_eq!(config.workflow_pollers(), 3);
+    assert_eq!(config.max_concurrent_workflow_tasks(), 10);
+    assert_eq!(config.shutdown_grace_period(), Duration::from_secs(5));
+    assert_eq!(config.log_format(), LogFormat::Pretty);
+}
+
+#[test]
+fn test_invalid_settings_are_rejected() {
+    let zero_slots = WorkerConfig {
+        max_concurrent_activities: Some(0),
+        ..Default::default()
+    };
+    assert_matches!(
+        zero_slots.validate(),
+        Err(WorkerConfigError::Zero("max_concurrent_activities"))
+    );
+
+    let negative_rate = WorkerConfig {
+        activities_per_second: Some(-1.0),
+        ..Default::default()
+    };
+    assert_matches!(
+        negative_rate.validate(),
+        Err(WorkerConfigError::InvalidRate(_))
+    );
+}
+
+#[test]
+fn test_sticky_cache_needs_two_workflow_pollers() {
+    let mut config = WorkerConfig {
+        workflow_pollers: Some(1),
+        ..Default::default()
+    };
+    assert_matches!(
+        config.validate(),
+        Err(WorkerConfigError::TooFewStickyPollers)
+    );
+
+    config.sticky_cache_size = Some(0);
+    config.validate().unwrap();
+}
+
+#[test]
+fn test_approval_policy_is_configurable() {
+    assert_eq!(
+        WorkerConfig::default().approval_policy(),
+        ApprovalPolicy::default()
+    );
+
+    let path = temp_file(
+        "worker-approval.toml",
+        "approval_threshold = \"2500.00\"\napproval_timeout = 3600\n",
+    );
+    let config = WorkerConfig::from_file(&path).unwrap();
+    let policy = config.approval_policy();
+    assert_eq!(policy.threshold, Decimal::new(250000, 2));
+    assert_eq!(policy.timeout, Duration::from_secs(3600));
+    assert!(!policy.requires_approval(Decimal::new(250000, 2)));
+    assert!(policy.requires_approval(Decimal::new(250001, 2)));
+
+    let cli = TestCli::parse_from(["test", "--approval-threshold", "50"]);
+    let policy = cli.worker.config().unwrap().approval_policy();
+    assert_eq!(policy.threshold, Decimal::from(50));
+    assert_eq!(policy.timeout, DEFAULT_APPROVAL_TIMEOUT);
+}
+
+#[test]
+fn test_invalid_approval_settings_are_rejected() {
+    let negative_threshold = WorkerConfig {
+        approval_threshold: Some(Decimal::from(-1)),
+        ..Default::default()
+    };
+    assert_matches!(
+        negative_threshold.validate(),
+        Err(WorkerConfigError::NegativeApprovalThreshold(_))
+    );
+
+    let no_time_to_decide = WorkerConfig {
+        approval_timeout: Some(0),
+        ..Default::default()
+    };
+    assert_matches!(
+        no_time_to_decide.validate(),
+        Err(WorkerConfigError::Zero("approval_timeout"))
+    );
+}
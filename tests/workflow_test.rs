@@ -1,18 +1,31 @@
+use assert_matches::assert_matches;
 use std::process::Stdio;
 use std::str::FromStr;
+use std::time::Duration;
 
 use money_transfer_project_template_rust::{
     activity::Activities,
-    shared::{MONEY_TRANSFER_TASK_QUEUE_NAME, PaymentDetails},
-    workflow::MoneyTransferWorkflow,
+    approval::DEFAULT_APPROVAL_TIMEOUT,
+    fees::{AccountType, FeeBreakdown, FeeEngine},
+    search_attributes::{
+        PAYMENT_DETAILS_MEMO, SEARCH_ATTRIBUTES, TRANSFER_STATUS, TransferStatus,
+        transfer_search_attributes,
+    },
+    shared::{
+        MONEY_TRANSFER_TASK_QUEUE_NAME, PaymentDetails, RoutedPaymentDetails, TransferResult,
+    },
+    workflow::{MoneyTransferWorkflow, RoutedMoneyTransferWorkflow, is_non_retryable},
 };
 use rust_decimal::Decimal;
-use temporal_test_harness::TestWorkflowEnvironment;
+use temporal_test_harness::{TestHarnessError, TestWorkflowEnvironment};
 use temporalio_client::{
     Client, ClientOptions, Connection, ConnectionOptions, WorkflowGetResultOptions,
     WorkflowStartOptions,
 };
-use temporalio_sdk::{Worker, WorkerOptions};
+use temporalio_common::protos::temporal::api::failure::v1::{
+    ActivityFailureInfo, ApplicationFailureInfo, Failure, TimeoutFailureInfo, failure::FailureInfo,
+};
+use temporalio_sdk::{ActivityExecutionError, Worker, WorkerOptions};
 use temporalio_sdk_core::{
     CoreRuntime, RuntimeOptions, Url,
     ephemeral_server::{TemporalDevServerConfig, default_cached_download},
@@ -28,14 +41,26 @@ fn test_payment_details() -> PaymentDetails {
     }
 }
 
+/// The fee the standard schedule charges on [`test_payment_details`]: 0.50, plus 1% of
+/// the 300 above 100.
+fn test_fee_breakdown() -> FeeBreakdown {
+    FeeEngine::standard()
+        .quote(AccountType::Personal, "USD", Decimal::from(400))
+        .unwrap()
+}
+
 #[tokio::test]
 async fn test_money_transfer_happy_path() {
     let mut env = TestWorkflowEnvironment::new();
     env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
     env.on_activity("Activities::withdraw")
         .returns("W1234567890");
     env.on_activity("Activities::deposit")
         .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
 
     env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
         .await
@@ -43,38 +68,115 @@ async fn test_money_transfer_happy_path() {
 
     assert!(env.is_workflow_completed());
     assert!(env.workflow_error().is_none());
-    let result: String = env.workflow_result().unwrap();
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.withdraw_transaction_id, "W1234567890");
+    assert_eq!(result.deposit_transaction_id, "D0987654321");
+    assert_eq!(result.fee_transaction_id.as_deref(), Some("D1122334455"));
+    assert_eq!(result.fee.fee, Decimal::new(350, 2));
+    assert_eq!(result.principal, Decimal::from(400));
+    assert_eq!(result.total_debited, Decimal::new(40350, 2));
     assert!(
-        result.contains("W1234567890"),
-        "Expected withdraw ID in output: {result}"
+        result.to_string().contains("fee 3.50 USD"),
+        "Expected fee in output: {result}"
     );
-    assert!(
-        result.contains("D0987654321"),
-        "Expected deposit ID in output: {result}"
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Completed)
+    );
+    assert_eq!(
+        env.memo::<PaymentDetails>(PAYMENT_DETAILS_MEMO)
+            .map(|details| details.reference_id),
+        Some("test-ref-001".to_string())
     );
 }
 
+#[tokio::test]
+async fn test_money_transfer_without_fee_skips_fee_posting() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    let mut free = test_fee_breakdown();
+    free.unrounded_fee = Decimal::ZERO;
+    free.fee = Decimal::ZERO;
+    env.on_activity("Activities::calculate_fee").returns(free);
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.workflow_error().is_none());
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.fee_transaction_id, None);
+    assert_eq!(result.total_debited, Decimal::from(400));
+}
+
 #[tokio::test]
 async fn test_money_transfer_deposit_fails() {
     let mut env = TestWorkflowEnvironment::new();
     env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
     env.on_activity("Activities::withdraw")
         .returns("W1234567890");
     env.on_activity("Activities::deposit")
         .returns_err("deposit failed");
+    env.on_activity("Activities::refund").returns("D5555555555");
 
     env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
         .await
         .expect("harness should not error");
 
     assert!(env.is_workflow_completed());
-    assert!(env.workflow_error().is_some());
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("withdrawal was refunded"),
+        "Expected compensation, got: {}",
+        error.message
+    );
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Compensated)
+    );
+}
+
+#[tokio::test]
+async fn test_money_transfer_deposit_and_refund_fail() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns_err("deposit failed");
+    env.on_activity("Activities::refund")
+        .returns_err("refund failed");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("refund failed"),
+        "Expected refund failure, got: {}",
+        error.message
+    );
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Failed)
+    );
 }
 
 #[tokio::test]
 async fn test_money_transfer_withdraw_fails() {
     let mut env = TestWorkflowEnvironment::new();
     env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
     env.on_activity("Activities::withdraw")
         .returns_err("withdraw failed");
 
@@ -86,12 +188,1079 @@ async fn test_money_transfer_withdraw_fails() {
     assert!(env.workflow_error().is_some());
 }
 
+#[tokio::test]
+async fn test_money_transfer_activity_inputs() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    let fee = test_fee_breakdown();
+    let fee_account = fee.fee_account.clone();
+    env.on_activity("Activities::calculate_fee")
+        .with_input(test_payment_details())
+        .returns(fee);
+    env.on_activity("Activities::withdraw")
+        .with_input(PaymentDetails {
+            amount: Decimal::new(40350, 2), // 403.50, fee included
+            ..test_payment_details()
+        })
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .with_input_matching(|details: &PaymentDetails| {
+            details.target_account == "43-812" && details.amount == Decimal::from(400)
+        })
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .with_input_matching(move |details: &PaymentDetails| {
+            details.target_account == fee_account && details.amount == Decimal::new(350, 2)
+        })
+        .returns("D1122334455");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("every activity should get the input its mock expects");
+
+    assert!(env.workflow_error().is_none());
+}
+
+#[tokio::test]
+async fn test_unexpected_activity_input_is_reported_with_a_diff() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    // The withdrawal includes the fee, so it isn't for the bare principal.
+    env.on_activity("Activities::withdraw")
+        .with_input(test_payment_details())
+        .returns("W1234567890");
+
+    let error = env
+        .execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .unwrap_err();
+
+    let TestHarnessError::InputMismatch { activity, details } = error else {
+        panic!("expected an input mismatch, got: {error}");
+    };
+    assert_eq!(activity, "Activities::withdraw");
+    assert!(
+        details.contains("- input.amount: \"400\"")
+            && details.contains("+ input.amount: \"403.50\""),
+        "{details}"
+    );
+    assert!(!details.contains("source_account"), "{details}");
+}
+
+#[tokio::test]
+async fn test_activity_input_of_the_wrong_type_does_not_match() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .with_input_matching(|amount: &Decimal| *amount > Decimal::ZERO)
+        .returns(test_fee_breakdown());
+
+    let error = env
+        .execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .unwrap_err();
+
+    assert_matches!(error, TestHarnessError::InputMismatch { details, .. } if details.contains("can't deserialize it as"));
+}
+
+#[tokio::test]
+async fn test_money_transfer_activity_calls_are_recorded() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let types: Vec<_> = env
+        .activity_calls()
+        .iter()
+        .map(|call| call.activity_type.as_str())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            "Activities::calculate_fee",
+            "Activities::withdraw",
+            "Activities::deposit",
+            "Activities::post_fee"
+        ]
+    );
+
+    let withdrawals = env.activity_calls_to("Activities::withdraw");
+    let [withdraw] = withdrawals.as_slice() else {
+        panic!("expected one withdrawal, got {withdrawals:?}");
+    };
+    assert_eq!(withdraw.attempt, 1);
+    assert_eq!(
+        withdraw
+            .input::<PaymentDetails>()
+            .map(|details| details.amount),
+        Some(Decimal::new(40350, 2))
+    );
+    assert_eq!(
+        withdraw.options.start_to_close_timeout,
+        Some(Duration::from_secs(60))
+    );
+    let retry_policy = withdraw.options.retry_policy.as_ref().unwrap();
+    assert_eq!(retry_policy.maximum_attempts, 5);
+}
+
+#[tokio::test]
+async fn test_withdraw_retried_after_transient_failures() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .fails_first(2, "bank unavailable")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    let start = env.now();
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.withdraw_transaction_id, "W1234567890");
+    assert_eq!(env.activity_calls_to("Activities::withdraw")[0].attempt, 3);
+    // Backing off 1s, then 2s.
+    assert_eq!(env.now(), start + Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn test_deposit_retries_stop_at_maximum_attempts() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns_err("bank unavailable");
+    env.on_activity("Activities::refund").returns("D5555555555");
+    let start = env.now();
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("withdrawal was refunded"),
+        "Expected compensation, got: {}",
+        error.message
+    );
+    assert_eq!(env.activity_calls_to("Activities::deposit")[0].attempt, 5);
+    // Backing off 1s, 2s and 4s, then capped at 5s.
+    assert_eq!(env.now(), start + Duration::from_secs(12));
+}
+
+#[tokio::test]
+async fn test_insufficient_funds_are_not_retried() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns_non_retryable_err("insufficient funds in 85-150");
+    let start = env.now();
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    assert!(env.workflow_error().is_some());
+    assert_eq!(env.activity_calls_to("Activities::withdraw")[0].attempt, 1);
+    assert_eq!(env.now(), start);
+}
+
+#[tokio::test]
+async fn test_invalid_deposit_account_is_not_retried() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .fails_first(1, "bank unavailable")
+        .returns_non_retryable_err("account 43-812 not found");
+    env.on_activity("Activities::refund").returns("D5555555555");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("withdrawal was refunded"),
+        "Expected compensation, got: {}",
+        error.message
+    );
+    assert_eq!(env.activity_calls_to("Activities::deposit")[0].attempt, 2);
+}
+
+#[tokio::test]
+async fn test_deposit_sees_its_attempt_number() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit").returns_with_attempt(
+        |details: PaymentDetails, attempt| match attempt {
+            1 | 2 => Err(format!("attempt {attempt} timed out")),
+            _ => Ok(format!("D-{}-{attempt}", details.reference_id)),
+        },
+    );
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.deposit_transaction_id, "D-test-ref-001-3");
+}
+
+#[tokio::test]
+async fn test_non_retryable_failure_overrides_the_retry_policy() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns_non_retryable_err("unsupported currency");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    assert!(env.workflow_error().is_some());
+    assert_eq!(
+        env.activity_calls_to("Activities::calculate_fee")[0].attempt,
+        1
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "unused mock: Activities::refund")]
+async fn test_assert_expectations_reports_unused_mocks() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    // Only needed if the deposit fails, which it doesn't.
+    env.on_activity("Activities::refund").returns("D5555555555");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected call: workflow scheduled activity `Activities::deposit`")]
+async fn test_assert_expectations_reports_unexpected_calls() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+
+    let _ = env
+        .execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await;
+
+    assert_eq!(env.activity_calls().len(), 3);
+    env.assert_expectations();
+}
+
+/// Mocks that behave like a bank holding 500.00 in the source account.
+fn mock_bank_with_balance_of_500(env: &mut TestWorkflowEnvironment) {
+    env.on_activity("Activities::calculate_fee")
+        .returns_with(|details: PaymentDetails| {
+            FeeEngine::standard().quote(AccountType::Personal, "USD", details.amount)
+        });
+    env.on_activity("Activities::withdraw")
+        .returns_with(|details: PaymentDetails| {
+            if details.amount > Decimal::from(500) {
+                Err(format!("insufficient funds to withdraw {}", details.amount))
+            } else {
+                Ok(format!("W-{}", details.reference_id))
+            }
+        });
+    env.on_activity("Activities::deposit")
+        .returns_with(|details: PaymentDetails| {
+            Ok::<_, String>(format!("D-{}", details.target_account))
+        });
+    env.on_activity("Activities::post_fee")
+        .returns_with(|details: PaymentDetails| Ok::<_, String>(format!("F-{}", details.amount)));
+}
+
+#[tokio::test]
+async fn test_money_transfer_against_computed_mocks() {
+    // (amount, whether the debit fits in the balance once the fee is added). The
+    // standard USD fee is 0.50 plus 1% of the part between 100 and 10,000, so 495 is
+    // debited 499.45 and 496 is debited 500.46.
+    for (amount, fits) in [(100, true), (495, true), (496, false), (900, false)] {
+        let fee = FeeEngine::standard()
+            .quote(AccountType::Personal, "USD", Decimal::from(amount))
+            .unwrap()
+            .fee;
+        assert_eq!(
+            Decimal::from(amount) + fee <= Decimal::from(500),
+            fits,
+            "{amount}"
+        );
+
+        let mut env = TestWorkflowEnvironment::new();
+        env.register_activities(Activities);
+        mock_bank_with_balance_of_500(&mut env);
+
+        let details = PaymentDetails {
+            amount: Decimal::from(amount),
+            ..test_payment_details()
+        };
+        env.execute_workflow::<MoneyTransferWorkflow>(details)
+            .await
+            .expect("harness should not error");
+
+        if fits {
+            let result: TransferResult = env.workflow_result().unwrap();
+            assert_eq!(result.withdraw_transaction_id, "W-test-ref-001");
+            assert_eq!(result.deposit_transaction_id, "D-43-812");
+            assert_eq!(
+                result.fee_transaction_id,
+                Some(format!("F-{}", result.fee.fee))
+            );
+        } else {
+            // The withdrawal was refused, so nothing was deposited or refunded.
+            assert!(env.workflow_error().is_some(), "{amount} should fail");
+            assert_eq!(
+                env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+                Some(TransferStatus::Failed),
+                "{amount}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_computed_mock_with_the_wrong_input_type_is_reported() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns_with(|account: String| Ok::<_, String>(account));
+
+    let error = env
+        .execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .unwrap_err();
+
+    assert_matches!(error, TestHarnessError::InputMismatch { activity, .. } if activity == "Activities::calculate_fee");
+}
+
+#[tokio::test]
+async fn test_money_transfer_invalid_input_fails_before_bank_calls() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    // No bank activities are mocked: scheduling any of them would fail the test.
+
+    let mut details = test_payment_details();
+    details.target_account = details.source_account.clone();
+    env.execute_workflow::<MoneyTransferWorkflow>(details)
+        .await
+        .expect("harness should not error");
+
+    assert!(env.is_workflow_completed());
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("invalid payment details"),
+        "Expected validation failure, got: {}",
+        error.message
+    );
+    assert!(!error.is_wft_failure);
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Failed)
+    );
+}
+
+fn activity_failure(cause: FailureInfo) -> ActivityExecutionError {
+    ActivityExecutionError::Failed(Box::new(Failure {
+        message: "activity failed".to_string(),
+        failure_info: Some(FailureInfo::ActivityFailureInfo(
+            ActivityFailureInfo::default(),
+        )),
+        cause: Some(Box::new(Failure {
+            message: "cause".to_string(),
+            failure_info: Some(cause),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }))
+}
+
+#[test]
+fn test_only_failures_the_activity_marked_non_retryable_count_as_non_retryable() {
+    let application = |non_retryable| {
+        FailureInfo::ApplicationFailureInfo(ApplicationFailureInfo {
+            non_retryable,
+            ..Default::default()
+        })
+    };
+
+    assert!(is_non_retryable(&activity_failure(application(true))));
+    // Out of attempts, or timed out: the payment may well be fine.
+    assert!(!is_non_retryable(&activity_failure(application(false))));
+    assert!(!is_non_retryable(&activity_failure(
+        FailureInfo::TimeoutFailureInfo(TimeoutFailureInfo::default())
+    )));
+    assert!(!is_non_retryable(&ActivityExecutionError::Cancelled(
+        Box::default()
+    )));
+}
+
+#[tokio::test]
+async fn test_money_transfer_cancelled_before_withdraw_exits_cleanly() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    // No bank activities are mocked: the workflow must not touch the accounts.
+    env.cancel_workflow_after_activity("Activities::calculate_fee", "customer changed their mind");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.is_workflow_completed());
+    let error = env.workflow_error().expect("workflow should be cancelled");
+    assert!(error.is_cancelled(), "Expected cancellation, got: {error}");
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Cancelled)
+    );
+}
+
+#[tokio::test]
+async fn test_money_transfer_cancelled_after_withdraw_refunds() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    // The whole debit goes back to the source account: the principal and the fee.
+    env.on_activity("Activities::refund")
+        .with_input(PaymentDetails {
+            amount: Decimal::from(400) + test_fee_breakdown().fee,
+            ..test_payment_details()
+        })
+        .returns("D5555555555");
+    env.cancel_workflow_after_activity("Activities::withdraw", "customer changed their mind");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let error = env.workflow_error().expect("workflow should be cancelled");
+    assert!(error.is_cancelled(), "Expected cancellation, got: {error}");
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Cancelled)
+    );
+    env.assert_expectations();
+}
+
+#[tokio::test]
+async fn test_money_transfer_cancelled_while_calculating_the_fee() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    // The fee service is down for the first attempts, so the cancellation arrives while
+    // the workflow is still waiting for a retry.
+    env.on_activity("Activities::calculate_fee")
+        .fails_first(2, "fee service unavailable")
+        .returns(test_fee_breakdown());
+    env.cancel_workflow_at(Duration::from_millis(500), "customer changed their mind");
+    let start = env.now();
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let error = env.workflow_error().expect("workflow should be cancelled");
+    assert!(error.is_cancelled(), "Expected cancellation, got: {error}");
+    // The fee calculation was abandoned rather than waited out.
+    assert_eq!(env.now(), start + Duration::from_millis(500));
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Cancelled)
+    );
+    let activities: Vec<_> = env
+        .activity_calls()
+        .iter()
+        .map(|call| call.activity_type.as_str())
+        .collect();
+    assert_eq!(activities, vec!["Activities::calculate_fee"]);
+    env.assert_expectations();
+}
+
+#[tokio::test]
+async fn test_money_transfer_cancelled_after_refund_failure_fails() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::refund")
+        .returns_err("bank unavailable");
+    env.cancel_workflow_after_activity("Activities::withdraw", "customer changed their mind");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(!error.is_cancelled());
+    assert!(
+        error.message.contains("refund failed"),
+        "Expected refund failure, got: {}",
+        error.message
+    );
+}
+
+#[tokio::test]
+async fn test_money_transfer_cancelled_after_deposit_is_too_late() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    env.cancel_workflow_after_activity("Activities::deposit", "customer changed their mind");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.workflow_error().is_none());
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.fee_transaction_id.as_deref(), Some("D1122334455"));
+    assert_eq!(
+        result.late_cancellation.as_deref(),
+        Some("customer changed their mind")
+    );
+    assert!(
+        result.to_string().contains("too late"),
+        "Expected late cancellation in output: {result}"
+    );
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Completed)
+    );
+}
+
+/// A transfer above the approval threshold.
+fn large_payment_details() -> PaymentDetails {
+    PaymentDetails {
+        amount: Decimal::from(5000),
+        ..test_payment_details()
+    }
+}
+
+fn large_fee_breakdown() -> FeeBreakdown {
+    FeeEngine::standard()
+        .quote(AccountType::Personal, "USD", Decimal::from(5000))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_large_transfer_waits_for_approval() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    env.signal_workflow_after_activity("Activities::calculate_fee", "approve", "alice");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.workflow_error().is_none());
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.approved_by.as_deref(), Some("alice"));
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Completed)
+    );
+}
+
+#[tokio::test]
+async fn test_large_transfer_without_approval_is_rejected_after_the_timeout() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    // No bank activities are mocked: an undecided transfer must not touch the accounts.
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    let start = env.now();
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error
+            .message
+            .contains("rejected: no decision within 72 hours"),
+        "Expected rejection, got: {}",
+        error.message
+    );
+    assert_eq!(env.now(), start + DEFAULT_APPROVAL_TIMEOUT);
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Rejected)
+    );
+    // New runs are marked as using the approval policy, so older runs replay without it.
+    assert_eq!(
+        env.search_attribute::<Vec<String>>("TemporalChangeVersion"),
+        Some(vec!["approval-policy".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn test_large_transfer_rejected() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    // No bank activities are mocked: a rejected transfer must not touch the accounts.
+    env.signal_workflow_after_activity(
+        "Activities::calculate_fee",
+        "reject",
+        "beneficiary not verified",
+    );
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("rejected: beneficiary not verified"),
+        "Expected rejection, got: {}",
+        error.message
+    );
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Rejected)
+    );
+}
+
+#[tokio::test]
+async fn test_large_transfer_cancelled_while_awaiting_approval() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    env.cancel_workflow_after_activity("Activities::calculate_fee", "no approver available");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let error = env.workflow_error().expect("workflow should be cancelled");
+    assert!(error.is_cancelled(), "Expected cancellation, got: {error}");
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Cancelled)
+    );
+}
+
+#[tokio::test]
+async fn test_large_transfer_approved_an_hour_later() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    // Only the first decision counts.
+    env.signal_workflow_at(Duration::from_secs(3600), "approve", "alice");
+    env.signal_workflow_at(Duration::from_secs(7200), "reject", "too late");
+    let start = env.now();
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.approved_by.as_deref(), Some("alice"));
+    assert_eq!(env.now(), start + Duration::from_secs(3600));
+}
+
+#[tokio::test]
+async fn test_large_transfer_rejected_after_a_day() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    env.signal_workflow_at(Duration::from_secs(86_400), "reject", "no response");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(error.message.contains("no response"), "{}", error.message);
+    assert_eq!(
+        env.search_attribute::<TransferStatus>(TRANSFER_STATUS),
+        Some(TransferStatus::Rejected)
+    );
+}
+
+#[tokio::test]
+async fn test_large_transfer_cancelled_before_anyone_approves() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    env.cancel_workflow_at(Duration::from_secs(600), "no approver available");
+    env.signal_workflow_at(Duration::from_secs(1200), "approve", "alice");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let error = env.workflow_error().expect("workflow should be cancelled");
+    assert!(error.is_cancelled(), "Expected cancellation, got: {error}");
+}
+
+#[tokio::test]
+async fn test_large_transfer_approved_by_update() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    let approval = env.update_workflow_at(Duration::from_secs(3600), "approve_pending", "alice");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.approved_by.as_deref(), Some("alice"));
+    let approval = env.update_outcome(&approval).unwrap();
+    assert_eq!(approval.result::<String>().unwrap(), "alice");
+
+    let status_at =
+        |secs| env.query_workflow_at::<TransferStatus>(Duration::from_secs(secs), "status", ());
+    assert_eq!(
+        status_at(1800).await.unwrap(),
+        TransferStatus::AwaitingApproval
+    );
+    assert_eq!(
+        env.query_workflow::<TransferStatus>("status", ())
+            .await
+            .unwrap(),
+        TransferStatus::Completed
+    );
+}
+
+#[tokio::test]
+async fn test_large_transfer_rejected_by_update() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(large_fee_breakdown());
+    let rejection = env.update_workflow_at(
+        Duration::from_secs(3600),
+        "reject_pending",
+        "counterparty unknown",
+    );
+
+    env.execute_workflow::<MoneyTransferWorkflow>(large_payment_details())
+        .await
+        .expect("harness should not error");
+
+    env.assert_expectations();
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("rejected: counterparty unknown"),
+        "Expected rejection, got: {}",
+        error.message
+    );
+    let rejection = env.update_outcome(&rejection).unwrap();
+    assert_eq!(
+        rejection.result::<String>().unwrap(),
+        "counterparty unknown"
+    );
+}
+
+#[tokio::test]
+async fn test_rejection_update_refused_below_the_threshold() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    let rejection =
+        env.update_workflow_after_activity("Activities::calculate_fee", "reject_pending", "no");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.workflow_error().is_none());
+    let rejection = env.update_outcome(&rejection).unwrap();
+    assert!(!rejection.is_accepted());
+}
+
+#[tokio::test]
+async fn test_approval_update_rejected_once_money_has_moved() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::calculate_fee")
+        .returns(test_fee_breakdown());
+    env.on_activity("Activities::withdraw")
+        .returns("W1234567890");
+    env.on_activity("Activities::deposit")
+        .returns("D0987654321");
+    env.on_activity("Activities::post_fee")
+        .returns("D1122334455");
+    let approval =
+        env.update_workflow_after_activity("Activities::withdraw", "approve_pending", "mallory");
+
+    env.execute_workflow::<MoneyTransferWorkflow>(test_payment_details())
+        .await
+        .expect("harness should not error");
+
+    let result: TransferResult = env.workflow_result().unwrap();
+    assert_eq!(result.approved_by, None);
+    let approval = env.update_outcome(&approval).unwrap();
+    assert!(!approval.is_accepted());
+    let error = approval.result::<String>().unwrap_err();
+    assert!(
+        error.to_string().contains("not awaiting approval"),
+        "{error}"
+    );
+}
+
+/// Build a RoutedPaymentDetails input for tests: source → clearing → correspondent → target.
+fn test_routed_payment_details() -> RoutedPaymentDetails {
+    RoutedPaymentDetails {
+        amount: Decimal::from(400),
+        route: vec![
+            "85-150".to_string(),
+            "10-001".to_string(),
+            "20-002".to_string(),
+            "43-812".to_string(),
+        ],
+        reference_id: "test-ref-002".to_string(),
+    }
+}
+
+#[test]
+fn test_routed_payment_legs() {
+    let legs = test_routed_payment_details().legs();
+
+    assert_eq!(legs.len(), 3);
+    assert_eq!(legs[0].source_account, "85-150");
+    assert_eq!(legs[0].target_account, "10-001");
+    assert_eq!(legs[2].source_account, "20-002");
+    assert_eq!(legs[2].target_account, "43-812");
+    assert_eq!(legs[1].reference_id, "test-ref-002-leg-2");
+}
+
+#[tokio::test]
+async fn test_routed_transfer_happy_path() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    for leg in 1..=3 {
+        env.on_activity("Activities::withdraw")
+            .returns(format!("W{leg}"));
+        env.on_activity("Activities::deposit")
+            .returns(format!("D{leg}"));
+    }
+
+    env.execute_workflow::<RoutedMoneyTransferWorkflow>(test_routed_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.is_workflow_completed());
+    assert!(env.workflow_error().is_none());
+    let result: String = env.workflow_result().unwrap();
+    assert!(
+        result.contains("over 3 legs"),
+        "Expected leg count in output: {result}"
+    );
+    assert!(
+        result.contains("W1, D1, W2, D2, W3, D3"),
+        "Expected every leg's transaction IDs in output: {result}"
+    );
+}
+
+#[tokio::test]
+async fn test_routed_transfer_last_deposit_fails_unwinds_in_reverse() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    for leg in 1..=2 {
+        env.on_activity("Activities::withdraw")
+            .returns(format!("W{leg}"));
+        env.on_activity("Activities::deposit")
+            .returns(format!("D{leg}"));
+    }
+    env.on_activity("Activities::withdraw").returns("W3");
+    env.on_activity("Activities::deposit")
+        .returns_err("deposit failed");
+    // Refund the failed leg, then reverse leg 2 and leg 1.
+    env.on_activity("Activities::refund").returns("R3");
+    env.on_activity("Activities::withdraw").returns("X2");
+    env.on_activity("Activities::refund").returns("R2");
+    env.on_activity("Activities::withdraw").returns("X1");
+    env.on_activity("Activities::refund").returns("R1");
+
+    env.execute_workflow::<RoutedMoneyTransferWorkflow>(test_routed_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.is_workflow_completed());
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("leg 3 (20-002 -> 43-812) failed"),
+        "Expected failing leg in error: {}",
+        error.message
+    );
+    assert!(
+        error.message.contains("unwound 2 completed leg(s)"),
+        "Expected unwound leg count in error: {}",
+        error.message
+    );
+}
+
+#[tokio::test]
+async fn test_routed_transfer_withdraw_fails_unwinds_completed_legs() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+    env.on_activity("Activities::withdraw").returns("W1");
+    env.on_activity("Activities::deposit").returns("D1");
+    env.on_activity("Activities::withdraw")
+        .returns_err("withdraw failed");
+    env.on_activity("Activities::withdraw").returns("X1");
+    env.on_activity("Activities::refund").returns("R1");
+
+    env.execute_workflow::<RoutedMoneyTransferWorkflow>(test_routed_payment_details())
+        .await
+        .expect("harness should not error");
+
+    assert!(env.is_workflow_completed());
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("leg 2 (10-001 -> 20-002) failed"),
+        "Expected failing leg in error: {}",
+        error.message
+    );
+    assert!(
+        error.message.contains("unwound 1 completed leg(s)"),
+        "Expected unwound leg count in error: {}",
+        error.message
+    );
+}
+
+#[tokio::test]
+async fn test_routed_transfer_rejects_short_route() {
+    let mut env = TestWorkflowEnvironment::new();
+    env.register_activities(Activities);
+
+    let mut input = test_routed_payment_details();
+    input.route.truncate(1);
+    env.execute_workflow::<RoutedMoneyTransferWorkflow>(input)
+        .await
+        .expect("harness should not error");
+
+    assert!(env.is_workflow_completed());
+    let error = env.workflow_error().expect("workflow should fail");
+    assert!(
+        error.message.contains("at least a source and a target"),
+        "Expected route error: {}",
+        error.message
+    );
+}
+
 #[tokio::test]
 async fn test_money_transfer_with_dev_server() {
     // 1. Start ephemeral dev server
     let config = TemporalDevServerConfig::builder()
         .exe(default_cached_download())
         .log(("pretty".to_string(), "error".to_string()))
+        .extra_args(
+            SEARCH_ATTRIBUTES
+                .iter()
+                .flat_map(|(name, kind)| {
+                    ["--search-attribute".to_string(), format!("{name}={kind}")]
+                })
+                .collect(),
+        )
         .build();
 
     let mut server = config
@@ -141,7 +1310,7 @@ async fn test_money_transfer_with_dev_server() {
     // 5. Run worker and workflow execution concurrently.
     //    Worker is !Send so we cannot tokio::spawn it; instead we use tokio::select!
     //    on a current_thread runtime so both futures run on the same thread.
-    let result: String = tokio::select! {
+    let result: TransferResult = tokio::select! {
         worker_result = worker.run() => {
             panic!("Worker exited unexpectedly: {:?}", worker_result);
         }
@@ -158,10 +1327,11 @@ async fn test_money_transfer_with_dev_server() {
                 MONEY_TRANSFER_TASK_QUEUE_NAME,
                 workflow_id,
             )
+            .search_attributes(transfer_search_attributes(&payment))
             .build();
 
             let handle = starter_client
-                .start_workflow(MoneyTransferWorkflow, payment, options)
+                .start_workflow(MoneyTransferWorkflow::run, payment, options)
                 .await
                 .expect("Failed to start workflow");
 
@@ -177,6 +1347,7 @@ async fn test_money_transfer_with_dev_server() {
     };
 
     // 6. Assert on the result
+    let result = result.to_string();
     assert!(
         result.contains("Transfer complete"),
         "Expected 'Transfer complete' in result: {result}"